@@ -1,6 +1,7 @@
 #![allow(clippy::result_large_err)]
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 
 declare_id!("GzurKGq4dorjxwyL4MMBqUksY7ZtDaZXQM8wTkfJzFpm");
 
@@ -11,42 +12,279 @@ pub mod crud {
 
   pub fn initialize_journal_entry(ctx: Context<CreateEntry>, title: String, message: String) -> Result<()> {
 
+    JournalEntryState::validate(&title, &message)?;
+
     let journal_entry = &mut ctx.accounts.journal;
     journal_entry.owner = *ctx.accounts.owner.key;
     journal_entry.title = title;
     journal_entry.message = message;
+    journal_entry.last_modified = Clock::get()?.unix_timestamp;
+
+    let counter = &mut ctx.accounts.counter;
+    journal_entry.history = JournalEntryState::new_history(&ctx.accounts.owner.key(), counter.entry_count);
+    counter.entry_count += 1;
 
     Ok(())
   }  
 
-  pub fn update_journal(ctx: Context<UpdateEntry>, _title: String, message: String) -> Result<()> {
+  pub fn update_journal(ctx: Context<UpdateEntry>, title: String, message: String) -> Result<()> {
     
+    JournalEntryState::validate(&title, &message)?;
+
     let journal_update = &mut ctx.accounts.update_journal;
+    let address = journal_update.key();
+    journal_update.save_revision(&address, &mut ctx.accounts.revision)?;
     journal_update.message = message;
+    journal_update.last_modified = Clock::get()?.unix_timestamp;
 
     Ok(())
     
   }
 
-  pub fn delete_journal_entry(_ctx: Context<DeleteEntry>, _title: String) -> Result<()> {
+  // Restoring is an edit too, so the message it replaces becomes the newest revision
+  pub fn restore_revision(ctx: Context<RestoreRevision>, _title: String, _revision_number: u64) -> Result<()> {
+
+    let message = ctx.accounts.revision.message.clone();
+    let journal_entry = &mut ctx.accounts.journal;
+    let address = journal_entry.key();
+    journal_entry.save_revision(&address, &mut ctx.accounts.new_revision)?;
+    journal_entry.message = message;
+    journal_entry.last_modified = Clock::get()?.unix_timestamp;
+
+    Ok(())
+  }
+
+  // The title is part of the entry's address, so renaming moves the entry to a new
+  // account and closes the old one
+  pub fn update_journal_title(ctx: Context<RenameEntry>, _title: String, new_title: String) -> Result<()> {
+
+    let old_entry = &ctx.accounts.journal;
+    JournalEntryState::validate(&new_title, &old_entry.message)?;
+
+    let message = old_entry.message.clone();
+    let history = old_entry.history_key(&old_entry.key());
+    let revision_count = old_entry.revision_count;
+    let open_revisions = old_entry.open_revisions;
+    let chunk_count = old_entry.chunk_count;
+    let new_entry = &mut ctx.accounts.new_journal;
+    new_entry.owner = *ctx.accounts.owner.key;
+    new_entry.title = new_title;
+    new_entry.message = message;
+    new_entry.last_modified = Clock::get()?.unix_timestamp;
+    new_entry.history = history;
+    new_entry.revision_count = revision_count;
+    new_entry.open_revisions = open_revisions;
+    new_entry.chunk_count = chunk_count;
+
+    Ok(())
+  }
+
+  // Long entries continue past `message` in chunk accounts, each sized to its own text
+  pub fn append_chunk(ctx: Context<AppendChunk>, _title: String, text: String) -> Result<()> {
+
+    require!(!text.is_empty(), ErrorCode::EmptyChunk);
+    require!(text.len() <= JournalChunk::MAX_TEXT_LENGTH, ErrorCode::ChunkTooLong);
+
+    let journal_entry = &mut ctx.accounts.journal;
+    ctx.accounts.chunk.set_inner(JournalChunk {
+      history: journal_entry.history_key(&journal_entry.key()),
+      index: journal_entry.chunk_count,
+      text,
+    });
+    journal_entry.chunk_count += 1;
+    journal_entry.last_modified = Clock::get()?.unix_timestamp;
+
+    Ok(())
+  }
+
+  // Drops every chunk from `chunk_count` on. Pass those chunks as remaining accounts, in order.
+  pub fn truncate<'info>(ctx: Context<'_, '_, 'info, 'info, Truncate<'info>>, _title: String, chunk_count: u32) -> Result<()> {
+
+    let journal_entry = &mut ctx.accounts.journal;
+    require!(chunk_count <= journal_entry.chunk_count, ErrorCode::ChunkNotFound);
+    require!(
+      ctx.remaining_accounts.len() == (journal_entry.chunk_count - chunk_count) as usize,
+      ErrorCode::ChunkAccountsMismatch
+    );
+
+    let history = journal_entry.history_key(&journal_entry.key());
+    for (index, chunk_info) in (chunk_count..).zip(ctx.remaining_accounts.iter()) {
+      let (address, _) = JournalChunk::find_address(&history, index);
+      require_keys_eq!(chunk_info.key(), address, ErrorCode::ChunkAccountsMismatch);
+
+      let chunk = Account::<JournalChunk>::try_from(chunk_info)?;
+      chunk.close(ctx.accounts.owner.to_account_info())?;
+    }
+
+    journal_entry.chunk_count = chunk_count;
+    journal_entry.last_modified = Clock::get()?.unix_timestamp;
+
+    Ok(())
+  }
+
+  // Closes the entry's revisions passed as remaining accounts and refunds their rent. Entries
+  // with more revisions than fit in one transaction can be cleared in batches before deleting.
+  pub fn close_revisions<'info>(ctx: Context<'_, '_, 'info, 'info, CloseRevisions<'info>>, _title: String) -> Result<()> {
+
+    close_revision_accounts(&mut ctx.accounts.journal, &ctx.accounts.owner, ctx.remaining_accounts)
+  }
+
+  // `close = owner` on the account wipes the entry and refunds its rent. Pass the revisions
+  // still open as remaining accounts to close them too; none may be left once the entry is gone.
+  pub fn delete_journal_entry<'info>(ctx: Context<'_, '_, 'info, 'info, DeleteEntry<'info>>, _title: String) -> Result<()> {
+
+    let journal_entry = &mut ctx.accounts.delete_journal_entry;
+    close_revision_accounts(journal_entry, &ctx.accounts.owner, ctx.remaining_accounts)?;
+    require!(journal_entry.open_revisions == 0, ErrorCode::EntryHasRevisions);
+
     Ok(())
   }  
 }
 
+// Each revision is checked to belong to the entry's history, so another entry's revisions can't
+// be closed, and a closed revision can't be passed again to count twice
+fn close_revision_accounts<'info>(
+  journal_entry: &mut Account<'info, JournalEntryState>,
+  owner: &Signer<'info>,
+  revision_infos: &'info [AccountInfo<'info>],
+) -> Result<()> {
+
+  let history = journal_entry.history_key(&journal_entry.key());
+  for revision_info in revision_infos {
+    let revision = Account::<JournalRevision>::try_from(revision_info)?;
+    require_keys_eq!(revision.history, history, ErrorCode::RevisionAccountsMismatch);
+    revision.close(owner.to_account_info())?;
+    journal_entry.open_revisions -= 1;
+  }
+
+  Ok(())
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct JournalEntryState {
   pub owner: Pubkey,
 
-  #[max_len(100)]
+  // Titles are PDA seeds, which are capped at 32 bytes. A longer title fails when the
+  // entry's address is derived, before any instruction runs, so there is no error for it
+  #[max_len(32)]
   pub title: String,
 
   #[max_len(1000)]
   pub message: String,
+
+  pub last_modified: i64,
+
+  // Revisions are seeded by this key rather than the entry's address, so they stay with the
+  // entry across renames and never pass to a later entry that reuses the title
+  pub history: Pubkey,
+
+  pub revision_count: u64,
+
+  // Revisions not closed yet, which must reach 0 before the entry can be deleted
+  pub open_revisions: u64,
+
+  pub chunk_count: u32,
+}
+
+// Accounts are sized to the text they hold rather than to `max_len`, so `max_len` only
+// sets the upper bound. Chunks are seeded by the entry's history key, so a new entry at a
+// reused title starts from index 0 without running into a renamed entry's chunks.
+#[account]
+#[derive(InitSpace)]
+pub struct JournalChunk {
+  pub history: Pubkey,
+
+  pub index: u32,
+
+  #[max_len(900)]
+  pub text: String,
+}
+
+impl JournalChunk {
+  // Leaves room in a transaction for the signature, accounts and instruction data around the text
+  pub const MAX_TEXT_LENGTH: usize = 900;
+
+  pub fn space_for(text: &str) -> usize {
+    8 + Self::INIT_SPACE - Self::MAX_TEXT_LENGTH + text.len()
+  }
+
+  pub fn find_address(history: &Pubkey, index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"chunk", history.as_ref(), &index.to_le_bytes()], &crate::ID)
+  }
+}
+
+// How many entries the owner has ever created, which makes each entry's history key unique
+#[account]
+#[derive(InitSpace)]
+pub struct JournalCounter {
+  pub entry_count: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct JournalRevision {
+  pub history: Pubkey,
+
+  pub revision: u64,
+
+  #[max_len(1000)]
+  pub message: String,
+
+  pub last_modified: i64,
+}
+
+impl JournalRevision {
+  pub fn space_for(message: &str) -> usize {
+    8 + Self::INIT_SPACE - JournalEntryState::MAX_MESSAGE_LENGTH + message.len()
+  }
+}
+
+impl JournalEntryState {
+  pub const MAX_TITLE_LENGTH: usize = 32;
+  pub const MAX_MESSAGE_LENGTH: usize = 1000;
+
+  pub fn space_for(title: &str, message: &str) -> usize {
+    8 + Self::INIT_SPACE - Self::MAX_TITLE_LENGTH - Self::MAX_MESSAGE_LENGTH + title.len() + message.len()
+  }
+
+  pub fn validate(title: &str, message: &str) -> Result<()> {
+    require!(!title.is_empty(), ErrorCode::EmptyTitle);
+    require!(message.len() <= Self::MAX_MESSAGE_LENGTH, ErrorCode::MessageTooLong);
+
+    Ok(())
+  }
+
+  // Hashing the owner's creation count gives every entry its own key, even one created
+  // at the address of a deleted or renamed entry
+  pub fn new_history(owner: &Pubkey, entry_count: u64) -> Pubkey {
+    Pubkey::new_from_array(hashv(&[b"history", owner.as_ref(), &entry_count.to_le_bytes()]).to_bytes())
+  }
+
+  // Entries written before revisions existed have no history key yet and use their own address
+  pub fn history_key(&self, address: &Pubkey) -> Pubkey {
+    if self.history == Pubkey::default() { *address } else { self.history }
+  }
+
+  // Copies the current message into `revision`, which must be the account for `revision_count`
+  pub fn save_revision(&mut self, address: &Pubkey, revision: &mut Account<JournalRevision>) -> Result<()> {
+
+    self.history = self.history_key(address);
+    revision.set_inner(JournalRevision {
+      history: self.history,
+      revision: self.revision_count,
+      message: self.message.clone(),
+      last_modified: self.last_modified,
+    });
+    self.revision_count += 1;
+    self.open_revisions += 1;
+
+    Ok(())
+  }
 }
 
 #[derive(Accounts)]
-#[instruction(title: String)]
+#[instruction(title: String, message: String)]
 pub struct CreateEntry<'info> {
 
   #[account(mut)]
@@ -55,17 +293,26 @@ pub struct CreateEntry<'info> {
   #[account(
     init,
     payer = owner,
-    space = 8 + JournalEntryState::INIT_SPACE,
+    space = JournalEntryState::space_for(&title, &message),
     seeds = [title.as_bytes(), owner.key().as_ref()],
     bump
   )]
   pub journal: Account<'info, JournalEntryState>,
 
+  #[account(
+    init_if_needed,
+    payer = owner,
+    space = 8 + JournalCounter::INIT_SPACE,
+    seeds = [b"journal_counter", owner.key().as_ref()],
+    bump
+  )]
+  pub counter: Account<'info, JournalCounter>,
+
   pub system_program: Program<'info, System>
 }
 
 #[derive(Accounts)]
-#[instruction(title: String)]
+#[instruction(title: String, message: String)]
 pub struct UpdateEntry<'info>{
 
   #[account(mut)]
@@ -73,17 +320,162 @@ pub struct UpdateEntry<'info>{
 
   #[account(
     mut,
-    realloc = 8 + JournalEntryState::INIT_SPACE,
+    realloc = JournalEntryState::space_for(&title, &message),
     realloc::payer = owner,
     realloc::zero = true,
     seeds = [title.as_bytes(), owner.key().as_ref()],
-    bump
+    bump,
+    has_one = owner
   )]
   pub update_journal: Account<'info, JournalEntryState>,
 
+  #[account(
+    init,
+    payer = owner,
+    space = JournalRevision::space_for(&update_journal.message),
+    seeds = [
+      b"revision",
+      update_journal.history_key(&update_journal.key()).as_ref(),
+      update_journal.revision_count.to_le_bytes().as_ref()
+    ],
+    bump
+  )]
+  pub revision: Account<'info, JournalRevision>,
+
   pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(title: String, revision_number: u64)]
+pub struct RestoreRevision<'info> {
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    realloc = JournalEntryState::space_for(&title, &revision.message),
+    realloc::payer = owner,
+    realloc::zero = true,
+    seeds = [title.as_bytes(), owner.key().as_ref()],
+    bump,
+    has_one = owner
+  )]
+  pub journal: Account<'info, JournalEntryState>,
+
+  #[account(
+    seeds = [b"revision", journal.history_key(&journal.key()).as_ref(), revision_number.to_le_bytes().as_ref()],
+    bump
+  )]
+  pub revision: Account<'info, JournalRevision>,
+
+  #[account(
+    init,
+    payer = owner,
+    space = JournalRevision::space_for(&journal.message),
+    seeds = [
+      b"revision",
+      journal.history_key(&journal.key()).as_ref(),
+      journal.revision_count.to_le_bytes().as_ref()
+    ],
+    bump
+  )]
+  pub new_revision: Account<'info, JournalRevision>,
+
+  pub system_program: Program<'info, System>
+}
+
+#[derive(Accounts)]
+#[instruction(title: String, new_title: String)]
+pub struct RenameEntry<'info> {
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    seeds = [title.as_bytes(), owner.key().as_ref()],
+    bump,
+    has_one = owner,
+    close = owner
+  )]
+  pub journal: Account<'info, JournalEntryState>,
+
+  #[account(
+    init,
+    payer = owner,
+    space = JournalEntryState::space_for(&new_title, &journal.message),
+    seeds = [new_title.as_bytes(), owner.key().as_ref()],
+    bump
+  )]
+  pub new_journal: Account<'info, JournalEntryState>,
+
+  pub system_program: Program<'info, System>
+}
+
+#[derive(Accounts)]
+#[instruction(title: String, text: String)]
+pub struct AppendChunk<'info> {
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    seeds = [title.as_bytes(), owner.key().as_ref()],
+    bump,
+    has_one = owner
+  )]
+  pub journal: Account<'info, JournalEntryState>,
+
+  #[account(
+    init,
+    payer = owner,
+    space = JournalChunk::space_for(&text),
+    seeds = [
+      b"chunk",
+      journal.history_key(&journal.key()).as_ref(),
+      journal.chunk_count.to_le_bytes().as_ref()
+    ],
+    bump
+  )]
+  pub chunk: Account<'info, JournalChunk>,
+
+  pub system_program: Program<'info, System>
+}
+
+#[derive(Accounts)]
+#[instruction(title: String)]
+pub struct Truncate<'info> {
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    seeds = [title.as_bytes(), owner.key().as_ref()],
+    bump,
+    has_one = owner
+  )]
+  pub journal: Account<'info, JournalEntryState>,
+}
+
+#[derive(Accounts)]
+#[instruction(title: String)]
+pub struct CloseRevisions<'info> {
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    seeds = [title.as_bytes(), owner.key().as_ref()],
+    bump,
+    has_one = owner
+  )]
+  pub journal: Account<'info, JournalEntryState>,
+}
+
 #[derive(Accounts)]
 #[instruction(title: String)]
 pub struct DeleteEntry<'info> {
@@ -95,6 +487,8 @@ pub struct DeleteEntry<'info> {
     mut,
     seeds = [title.as_bytes(), owner.key().as_ref()],
     bump,
+    has_one = owner,
+    constraint = delete_journal_entry.chunk_count == 0 @ ErrorCode::EntryHasChunks,
     close = owner
   )]
   pub delete_journal_entry: Account<'info, JournalEntryState>,
@@ -102,4 +496,24 @@ pub struct DeleteEntry<'info> {
   pub system_program: Program<'info, System>
 }
 
-
+#[error_code]
+pub enum ErrorCode {
+  #[msg("Title cannot be empty.")]
+  EmptyTitle,
+  #[msg("Message can be at most 1000 bytes.")]
+  MessageTooLong,
+  #[msg("Chunk cannot be empty.")]
+  EmptyChunk,
+  #[msg("Chunk can be at most 900 bytes.")]
+  ChunkTooLong,
+  #[msg("The entry does not have that many chunks.")]
+  ChunkNotFound,
+  #[msg("Remaining accounts must be the chunks being removed, in order.")]
+  ChunkAccountsMismatch,
+  #[msg("Truncate the entry's chunks before deleting it.")]
+  EntryHasChunks,
+  #[msg("Remaining accounts must be revisions of this entry.")]
+  RevisionAccountsMismatch,
+  #[msg("Close the entry's revisions before deleting it.")]
+  EntryHasRevisions,
+}
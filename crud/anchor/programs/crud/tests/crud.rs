@@ -0,0 +1,319 @@
+use anchor_lang::{
+  prelude::*,
+  solana_program::{entrypoint::ProgramResult, instruction::Instruction, system_instruction, system_program},
+  InstructionData,
+};
+use crud::{ErrorCode, JournalChunk, JournalEntryState, JournalRevision};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+  instruction::InstructionError,
+  signature::{Keypair, Signer},
+  transaction::{Transaction, TransactionError},
+};
+
+// Anchor's entrypoint ties the accounts slice and the account data to one lifetime,
+// which the builtin processor signature cannot name.
+fn process_instruction<'a, 'b, 'c, 'd>(program_id: &'a Pubkey, accounts: &'b [AccountInfo<'c>], data: &'d [u8]) -> ProgramResult {
+  // SAFETY: the slice outlives this call and the program keeps no references past it.
+  let accounts: &'c [AccountInfo<'c>] = unsafe { std::mem::transmute(accounts) };
+  crud::entry(program_id, accounts, data)
+}
+
+struct Env {
+  context: ProgramTestContext,
+  owner: Keypair,
+}
+
+impl Env {
+  async fn new() -> Self {
+    let program_test = ProgramTest::new("crud", crud::ID, processor!(process_instruction));
+    let mut context = program_test.start_with_context().await;
+    let owner = Keypair::new();
+    let payer = context.payer.pubkey();
+
+    process(&mut context, &[system_instruction::transfer(&payer, &owner.pubkey(), 1_000_000_000)], &[])
+      .await
+      .unwrap();
+
+    Self { context, owner }
+  }
+
+  fn entry_address(&self, title: &str) -> Pubkey {
+    find_entry_address(&self.owner.pubkey(), title)
+  }
+
+  async fn entry(&mut self, title: &str) -> JournalEntryState {
+    self.fetch(self.entry_address(title)).await
+  }
+
+  async fn fetch<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+    let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+  }
+
+  async fn account_exists(&mut self, address: Pubkey) -> bool {
+    self.context.banks_client.get_account(address).await.unwrap().is_some()
+  }
+
+  async fn send(&mut self, accounts: impl ToAccountMetas, data: impl InstructionData) -> std::result::Result<(), BanksClientError> {
+    self.send_with_remaining(accounts, data, &[]).await
+  }
+
+  async fn send_with_remaining(
+    &mut self,
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+    remaining_accounts: &[Pubkey],
+  ) -> std::result::Result<(), BanksClientError> {
+    let mut accounts = accounts.to_account_metas(None);
+    accounts.extend(remaining_accounts.iter().map(|address| AccountMeta::new(*address, false)));
+    let instruction = Instruction { program_id: crud::ID, accounts, data: data.data() };
+
+    let owner = self.owner.insecure_clone();
+    process(&mut self.context, &[instruction], &[&owner]).await
+  }
+
+  async fn create(&mut self, title: &str, message: &str) {
+    let owner = self.owner.pubkey();
+    let accounts = crud::accounts::CreateEntry {
+      owner,
+      journal: self.entry_address(title),
+      counter: Pubkey::find_program_address(&[b"journal_counter", owner.as_ref()], &crud::ID).0,
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::InitializeJournalEntry { title: title.to_string(), message: message.to_string() };
+
+    self.send(accounts, data).await.unwrap();
+  }
+
+  async fn update(&mut self, title: &str, message: &str) -> std::result::Result<(), BanksClientError> {
+    let entry = self.entry(title).await;
+    let accounts = crud::accounts::UpdateEntry {
+      owner: self.owner.pubkey(),
+      update_journal: self.entry_address(title),
+      revision: find_revision_address(&entry.history, entry.revision_count),
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::UpdateJournal { title: title.to_string(), message: message.to_string() };
+
+    self.send(accounts, data).await
+  }
+
+  async fn restore(&mut self, title: &str, revision_number: u64) -> std::result::Result<(), BanksClientError> {
+    let entry = self.entry(title).await;
+    let accounts = crud::accounts::RestoreRevision {
+      owner: self.owner.pubkey(),
+      journal: self.entry_address(title),
+      revision: find_revision_address(&entry.history, revision_number),
+      new_revision: find_revision_address(&entry.history, entry.revision_count),
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::RestoreRevision { _title: title.to_string(), _revision_number: revision_number };
+
+    self.send(accounts, data).await
+  }
+
+  async fn rename(&mut self, title: &str, new_title: &str) -> std::result::Result<(), BanksClientError> {
+    let accounts = crud::accounts::RenameEntry {
+      owner: self.owner.pubkey(),
+      journal: self.entry_address(title),
+      new_journal: self.entry_address(new_title),
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::UpdateJournalTitle { _title: title.to_string(), new_title: new_title.to_string() };
+
+    self.send(accounts, data).await
+  }
+
+  async fn append(&mut self, title: &str, text: &str) {
+    let entry = self.entry(title).await;
+    let accounts = crud::accounts::AppendChunk {
+      owner: self.owner.pubkey(),
+      journal: self.entry_address(title),
+      chunk: JournalChunk::find_address(&entry.history, entry.chunk_count).0,
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::AppendChunk { _title: title.to_string(), text: text.to_string() };
+
+    self.send(accounts, data).await.unwrap();
+  }
+
+  async fn truncate(&mut self, title: &str, chunk_count: u32, chunks: &[Pubkey]) -> std::result::Result<(), BanksClientError> {
+    let accounts = crud::accounts::Truncate {
+      owner: self.owner.pubkey(),
+      journal: self.entry_address(title),
+    };
+    let data = crud::instruction::Truncate { _title: title.to_string(), chunk_count };
+
+    self.send_with_remaining(accounts, data, chunks).await
+  }
+
+  async fn close_revisions(&mut self, title: &str, revisions: &[Pubkey]) -> std::result::Result<(), BanksClientError> {
+    let accounts = crud::accounts::CloseRevisions {
+      owner: self.owner.pubkey(),
+      journal: self.entry_address(title),
+    };
+    let data = crud::instruction::CloseRevisions { _title: title.to_string() };
+
+    self.send_with_remaining(accounts, data, revisions).await
+  }
+
+  async fn delete(&mut self, title: &str, revisions: &[Pubkey]) -> std::result::Result<(), BanksClientError> {
+    let accounts = crud::accounts::DeleteEntry {
+      owner: self.owner.pubkey(),
+      delete_journal_entry: self.entry_address(title),
+      system_program: system_program::ID,
+    };
+    let data = crud::instruction::DeleteJournalEntry { _title: title.to_string() };
+
+    self.send_with_remaining(accounts, data, revisions).await
+  }
+}
+
+fn find_entry_address(owner: &Pubkey, title: &str) -> Pubkey {
+  Pubkey::find_program_address(&[title.as_bytes(), owner.as_ref()], &crud::ID).0
+}
+
+fn find_revision_address(history: &Pubkey, revision: u64) -> Pubkey {
+  Pubkey::find_program_address(&[b"revision", history.as_ref(), &revision.to_le_bytes()], &crud::ID).0
+}
+
+async fn process(context: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> std::result::Result<(), BanksClientError> {
+  // A fresh blockhash keeps otherwise identical transactions from being deduplicated.
+  let blockhash = context.get_new_latest_blockhash().await.unwrap();
+  let mut all_signers = vec![&context.payer];
+  all_signers.extend_from_slice(signers);
+
+  let transaction = Transaction::new_signed_with_payer(instructions, Some(&context.payer.pubkey()), &all_signers, blockhash);
+  context.banks_client.process_transaction(transaction).await
+}
+
+fn assert_custom_error(result: std::result::Result<(), BanksClientError>, code: u32) {
+  match result.unwrap_err().unwrap() {
+    TransactionError::InstructionError(_, InstructionError::Custom(actual)) => assert_eq!(actual, code),
+    other => panic!("expected custom error {code}, got {other:?}"),
+  }
+}
+
+#[tokio::test]
+async fn update_rejects_a_non_owner() {
+  let mut env = Env::new().await;
+  env.create("diary", "mine").await;
+  let entry = env.entry("diary").await;
+
+  // The attacker signs as owner but points at the victim's entry.
+  let attacker = Keypair::new();
+  let payer = env.context.payer.pubkey();
+  process(&mut env.context, &[system_instruction::transfer(&payer, &attacker.pubkey(), 100_000_000)], &[])
+    .await
+    .unwrap();
+  let instruction = Instruction {
+    program_id: crud::ID,
+    accounts: crud::accounts::UpdateEntry {
+      owner: attacker.pubkey(),
+      update_journal: env.entry_address("diary"),
+      revision: find_revision_address(&entry.history, entry.revision_count),
+      system_program: system_program::ID,
+    }
+    .to_account_metas(None),
+    data: crud::instruction::UpdateJournal { title: "diary".to_string(), message: "not yours".to_string() }.data(),
+  };
+
+  assert_custom_error(
+    process(&mut env.context, &[instruction], &[&attacker]).await,
+    anchor_lang::error::ErrorCode::ConstraintSeeds.into(),
+  );
+  assert_eq!(env.entry("diary").await.message, "mine");
+}
+
+#[tokio::test]
+async fn restoring_a_revision_saves_the_replaced_message() {
+  let mut env = Env::new().await;
+  env.create("diary", "first").await;
+  env.update("diary", "second").await.unwrap();
+
+  env.restore("diary", 0).await.unwrap();
+
+  let entry = env.entry("diary").await;
+  assert_eq!(entry.message, "first");
+  assert_eq!(entry.revision_count, 2);
+  let revision: JournalRevision = env.fetch(find_revision_address(&entry.history, 1)).await;
+  assert_eq!(revision.message, "second");
+}
+
+#[tokio::test]
+async fn rename_carries_revisions_and_chunks_over() {
+  let mut env = Env::new().await;
+  env.create("draft", "first").await;
+  env.update("draft", "second").await.unwrap();
+  env.append("draft", "more").await;
+  let history = env.entry("draft").await.history;
+
+  env.rename("draft", "final").await.unwrap();
+
+  assert!(!env.account_exists(env.entry_address("draft")).await);
+  let entry = env.entry("final").await;
+  assert_eq!((entry.title.as_str(), entry.message.as_str()), ("final", "second"));
+  assert_eq!((entry.history, entry.revision_count, entry.chunk_count), (history, 1, 1));
+  let chunk: JournalChunk = env.fetch(JournalChunk::find_address(&history, 0).0).await;
+  assert_eq!(chunk.text, "more");
+
+  env.restore("final", 0).await.unwrap();
+  assert_eq!(env.entry("final").await.message, "first");
+}
+
+#[tokio::test]
+async fn truncate_requires_the_removed_chunks_in_order() {
+  let mut env = Env::new().await;
+  env.create("diary", "start").await;
+  env.append("diary", "one").await;
+  env.append("diary", "two").await;
+  let history = env.entry("diary").await.history;
+  let chunks = [JournalChunk::find_address(&history, 0).0, JournalChunk::find_address(&history, 1).0];
+
+  assert_custom_error(env.truncate("diary", 0, &chunks[..1]).await, ErrorCode::ChunkAccountsMismatch.into());
+  assert_custom_error(env.truncate("diary", 0, &[chunks[1], chunks[0]]).await, ErrorCode::ChunkAccountsMismatch.into());
+  assert_eq!(env.entry("diary").await.chunk_count, 2);
+
+  env.truncate("diary", 0, &chunks).await.unwrap();
+  assert_eq!(env.entry("diary").await.chunk_count, 0);
+  assert!(!env.account_exists(chunks[0]).await);
+  assert!(!env.account_exists(chunks[1]).await);
+}
+
+#[tokio::test]
+async fn delete_requires_chunks_to_be_truncated_first() {
+  let mut env = Env::new().await;
+  env.create("diary", "start").await;
+  env.append("diary", "one").await;
+  let chunk = JournalChunk::find_address(&env.entry("diary").await.history, 0).0;
+
+  assert_custom_error(env.delete("diary", &[]).await, ErrorCode::EntryHasChunks.into());
+  assert!(env.account_exists(env.entry_address("diary")).await);
+
+  env.truncate("diary", 0, &[chunk]).await.unwrap();
+  env.delete("diary", &[]).await.unwrap();
+  assert!(!env.account_exists(env.entry_address("diary")).await);
+}
+
+#[tokio::test]
+async fn delete_requires_every_revision_to_be_closed() {
+  let mut env = Env::new().await;
+  env.create("diary", "first").await;
+  env.update("diary", "second").await.unwrap();
+  env.update("diary", "third").await.unwrap();
+  let history = env.entry("diary").await.history;
+  let revisions = [find_revision_address(&history, 0), find_revision_address(&history, 1)];
+
+  assert_custom_error(env.delete("diary", &revisions[..1]).await, ErrorCode::EntryHasRevisions.into());
+  assert!(env.account_exists(revisions[0]).await);
+
+  // A closed revision can't be passed again to make up for one still open.
+  env.close_revisions("diary", &revisions[..1]).await.unwrap();
+  assert_eq!(env.entry("diary").await.open_revisions, 1);
+  assert!(env.delete("diary", &revisions[..1]).await.is_err());
+
+  env.delete("diary", &revisions[1..]).await.unwrap();
+  assert!(!env.account_exists(env.entry_address("diary")).await);
+  assert!(!env.account_exists(revisions[1]).await);
+}
@@ -1,50 +1,262 @@
 use anchor_lang::context::Context;
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::{system_program, Discriminator};
+use unicode_normalization::UnicodeNormalization;
 
 declare_id!("9S2zkmKbBKt2DSwcvHexBZufQn4GtnTattrjVKq6bT6R");
 
 pub const ANCHOR_DISCRIMINATOR_SIZE: usize = 8;
+pub const MAX_COLOR_LENGTH: usize = 50;
+pub const MAX_HOBBIES: usize = 5;
+pub const MAX_HOBBY_LENGTH: usize = 50;
+pub const FAVORITES_VERSION: u8 = 1;
+/// Keeps a hobby index small enough to deserialize within the program's heap.
+pub const MAX_HOBBY_INDEX_MEMBERS: usize = 256;
+pub const FAVORITES_SPACE: usize = ANCHOR_DISCRIMINATOR_SIZE + Favorites::INIT_SPACE;
 
 #[program]
 pub mod favorites {
 
     use super::*;
 
-    pub fn set_favorites(
-        ctx: Context<SetFavorites>,
+    /// Pass the hobby index account of every old and new hobby as remaining accounts,
+    /// unless the favorites are private.
+    pub fn set_favorites<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetFavorites<'info>>,
         number: u64,
         color: String,
         hobbies: Vec<String>,
     ) -> Result<()> {
+        let color = validate_color(&color)?;
+        let hobbies = validate_hobbies(&hobbies)?;
         let user_public_key = ctx.accounts.user.key();
 
         msg!("Greetings From {}", ctx.program_id);
 
         msg!("User {}'s favorite number is {}, favorite color is {} and favorite hobbies are {:?}", user_public_key, number, color, hobbies);
 
-        ctx.accounts.favorites.set_inner(Favorites {
-            number,
-            color,
-            hobbies,
-        });
+        let favorites_info = ctx.accounts.favorites.to_account_info();
+        let (old_hobbies, is_private) = if favorites_info.data_is_empty() {
+            create_program_account(
+                &ctx.accounts.user,
+                &ctx.accounts.system_program,
+                &favorites_info,
+                FAVORITES_SPACE,
+                &[b"Favorite", user_public_key.as_ref(), &[ctx.bumps.favorites]],
+            )?;
+            (Vec::new(), false)
+        } else {
+            let favorites = load_favorites(&favorites_info)?;
+            (favorites.hobbies, favorites.is_private)
+        };
+
+        // Private favorites are kept out of the hobby indexes
+        if !is_private {
+            sync_hobby_indexes(
+                &ctx.accounts.user,
+                &ctx.accounts.system_program,
+                &old_hobbies,
+                &hobbies,
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        store_favorites(
+            &favorites_info,
+            &Favorites {
+                version: FAVORITES_VERSION,
+                number,
+                color,
+                hobbies,
+                is_private,
+            },
+        )
+    }
+
+    /// Hands the favorites back as return data, for CPI callers and simulated transactions.
+    pub fn get_favorites(context: Context<GetFavorites>) -> Result<Favorites> {
+        load_favorites(&context.accounts.phrase)
+    }
+
+    /// Reads anyone's favorites without their signature, unless they have made them private.
+    pub fn view_favorites(ctx: Context<ViewFavorites>, _owner: Pubkey) -> Result<Favorites> {
+        let favorites = load_favorites(&ctx.accounts.favorites)?;
+        require!(!favorites.is_private, ErrorCode::FavoritesArePrivate);
+
+        Ok(favorites)
+    }
+
+    /// Pass the index account of every hobby as remaining accounts. Going private takes
+    /// the user out of their hobbies' indexes and going public puts them back.
+    pub fn set_visibility<'info>(ctx: Context<'_, '_, '_, 'info, UpdateHobbies<'info>>, is_private: bool) -> Result<()> {
+        let mut favorites = load_favorites(&ctx.accounts.favorites)?;
+        if favorites.is_private != is_private {
+            let (old_hobbies, new_hobbies): (&[String], &[String]) = if is_private {
+                (&favorites.hobbies, &[])
+            } else {
+                (&[], &favorites.hobbies)
+            };
+            sync_hobby_indexes(
+                &ctx.accounts.user,
+                &ctx.accounts.system_program,
+                old_hobbies,
+                new_hobbies,
+                ctx.remaining_accounts,
+            )?;
+        }
+        favorites.is_private = is_private;
+
+        store_favorites(&ctx.accounts.favorites, &favorites)
+    }
+
+    pub fn update_number(ctx: Context<UpdateFavorites>, number: u64) -> Result<()> {
+        let mut favorites = load_favorites(&ctx.accounts.favorites)?;
+        favorites.number = number;
+
+        store_favorites(&ctx.accounts.favorites, &favorites)
+    }
+
+    pub fn update_color(ctx: Context<UpdateFavorites>, color: String) -> Result<()> {
+        let mut favorites = load_favorites(&ctx.accounts.favorites)?;
+        favorites.color = validate_color(&color)?;
+
+        store_favorites(&ctx.accounts.favorites, &favorites)
+    }
+
+    /// Pass the hobby's index account as the only remaining account, unless the
+    /// favorites are private.
+    pub fn add_hobby<'info>(ctx: Context<'_, '_, '_, 'info, UpdateHobbies<'info>>, hobby: String) -> Result<()> {
+        let hobby = validate_hobby(&hobby)?;
+
+        let mut favorites = load_favorites(&ctx.accounts.favorites)?;
+        require!(favorites.hobbies.len() < MAX_HOBBIES, ErrorCode::TooManyHobbies);
+        require!(!contains_hobby(&favorites.hobbies, &hobby), ErrorCode::DuplicateHobby);
+
+        if !favorites.is_private {
+            join_hobby_index(&ctx.accounts.user, &ctx.accounts.system_program, &hobby, ctx.remaining_accounts)?;
+        }
+        favorites.hobbies.push(hobby);
+
+        store_favorites(&ctx.accounts.favorites, &favorites)
+    }
+
+    /// Pass the hobby's index account as the only remaining account, unless the
+    /// favorites are private.
+    pub fn remove_hobby<'info>(ctx: Context<'_, '_, '_, 'info, UpdateHobbies<'info>>, hobby: String) -> Result<()> {
+        let hobby = normalize(&hobby);
+        let mut favorites = load_favorites(&ctx.accounts.favorites)?;
+        let index = favorites
+            .hobbies
+            .iter()
+            .position(|existing| is_same_hobby(existing, &hobby))
+            .ok_or(ErrorCode::HobbyNotFound)?;
+        let hobby = favorites.hobbies.remove(index);
+
+        if !favorites.is_private {
+            leave_hobby_index(&ctx.accounts.user, &hobby, ctx.remaining_accounts)?;
+        }
+
+        store_favorites(&ctx.accounts.favorites, &favorites)
+    }
+
+    /// Pass the index account of every hobby as remaining accounts, unless the favorites
+    /// are private. Unmigrated favorites can be deleted too.
+    pub fn delete_favorites<'info>(ctx: Context<'_, '_, '_, 'info, DeleteFavorites<'info>>) -> Result<()> {
+        let favorites_info = ctx.accounts.favorites.to_account_info();
+        let favorites = if favorites_info.data_len() < FAVORITES_SPACE {
+            migrate_legacy_favorites(&favorites_info.try_borrow_data()?)?
+        } else {
+            load_favorites(&favorites_info)?
+        };
+
+        if !favorites.is_private {
+            sync_hobby_indexes(
+                &ctx.accounts.user,
+                &ctx.accounts.system_program,
+                &favorites.hobbies,
+                &[],
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        close_program_account(&favorites_info, &ctx.accounts.user)
+    }
+
+    /// Rewrites favorites saved before the layout was versioned, growing the account to
+    /// the current size. The user pays the extra rent. Those favorites predate the hobby
+    /// indexes, so pass the index account of every hobby as remaining accounts to join them.
+    pub fn migrate_favorites<'info>(ctx: Context<'_, '_, '_, 'info, MigrateFavorites<'info>>) -> Result<()> {
+        let favorites_info = ctx.accounts.favorites.to_account_info();
+        let favorites = migrate_legacy_favorites(&favorites_info.try_borrow_data()?)?;
+
+        let rent_due = Rent::get()?
+            .minimum_balance(FAVORITES_SPACE)
+            .saturating_sub(favorites_info.lamports());
+        if rent_due > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: favorites_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+
+        favorites_info.realloc(FAVORITES_SPACE, true)?;
+        favorites.try_serialize(&mut &mut favorites_info.try_borrow_mut_data()?[..])?;
+
+        sync_hobby_indexes(
+            &ctx.accounts.user,
+            &ctx.accounts.system_program,
+            &[],
+            &favorites.hobbies,
+            ctx.remaining_accounts,
+        )
+    }
+
+    pub fn follow(ctx: Context<Follow>, target: Pubkey) -> Result<()> {
+        let follower = ctx.accounts.follower.key();
+        require!(follower != target, ErrorCode::CannotFollowSelf);
+
+        ctx.accounts.follow.set_inner(FollowRecord { follower, target });
+
+        let follower_profile = &mut ctx.accounts.follower_profile;
+        follower_profile.following_count = follower_profile
+            .following_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CounterOverflow)?;
+
+        let target_profile = &mut ctx.accounts.target_profile;
+        target_profile.follower_count = target_profile
+            .follower_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CounterOverflow)?;
 
         Ok(())
     }
 
-    pub fn get_favorites(context: Context<GetFavorites>) -> Result<()> {
-        let favorites = &context.accounts.phrase;
+    pub fn unfollow(ctx: Context<Unfollow>, _target: Pubkey) -> Result<()> {
+        let follower_profile = &mut ctx.accounts.follower_profile;
+        follower_profile.following_count = follower_profile.following_count.saturating_sub(1);
 
-        msg!("User's favorite number is: {}", favorites.number);
-        msg!("User's favorite color is: {}", favorites.color);
-        msg!("User's hobbies are: {:?}", favorites.hobbies);
+        let target_profile = &mut ctx.accounts.target_profile;
+        target_profile.follower_count = target_profile.follower_count.saturating_sub(1);
 
         Ok(())
     }
 }
 
+/// Bump `FAVORITES_VERSION` whenever this layout changes and teach `migrate_favorites`
+/// to upgrade the previous one.
 #[account]
 #[derive(InitSpace)]
 pub struct Favorites {
+    pub version: u8,
+
     pub number: u64,
 
     #[max_len(50)]
@@ -52,6 +264,322 @@ pub struct Favorites {
 
     #[max_len(5, 50)]
     pub hobbies: Vec<String>,
+
+    /// Private favorites can only be read by their owner, through `get_favorites`
+    pub is_private: bool,
+}
+
+/// Social counters for a user, created the first time they follow or are followed.
+#[account]
+#[derive(InitSpace)]
+pub struct Profile {
+    pub follower_count: u64,
+    pub following_count: u64,
+}
+
+/// Exists while `follower` follows `target`.
+#[account]
+#[derive(InitSpace)]
+pub struct FollowRecord {
+    pub follower: Pubkey,
+    pub target: Pubkey,
+}
+
+/// Up to `MAX_HOBBY_INDEX_MEMBERS` users with public favorites who list a hobby, at the PDA
+/// `[b"hobby", hash(hobby_key(hobby))]`. Users who add the hobby once it is full keep it
+/// in their favorites but are not indexed. The account grows and shrinks with its
+/// members and is closed when the last one leaves.
+#[account]
+pub struct HobbyIndex {
+    pub hobby: String,
+    pub users: Vec<Pubkey>,
+}
+
+impl HobbyIndex {
+    pub fn space(&self) -> usize {
+        ANCHOR_DISCRIMINATOR_SIZE + 4 + self.hobby.len() + 4 + self.users.len() * 32
+    }
+}
+
+/// The layout accounts were created with before `version` existed. These accounts are
+/// shorter than `FAVORITES_SPACE`, which is how `migrate_favorites` recognises them.
+#[derive(AnchorDeserialize)]
+struct LegacyFavorites {
+    number: u64,
+    color: String,
+    hobbies: Vec<String>,
+}
+
+/// Reads an unversioned favorites account and returns it in the current layout.
+pub fn migrate_legacy_favorites(data: &[u8]) -> Result<Favorites> {
+    require!(data.len() < FAVORITES_SPACE, ErrorCode::AlreadyMigrated);
+    require!(
+        data.len() >= ANCHOR_DISCRIMINATOR_SIZE && data[..ANCHOR_DISCRIMINATOR_SIZE] == Favorites::DISCRIMINATOR,
+        anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+    );
+
+    let legacy = LegacyFavorites::deserialize(&mut &data[ANCHOR_DISCRIMINATOR_SIZE..])?;
+
+    Ok(Favorites {
+        version: FAVORITES_VERSION,
+        number: legacy.number,
+        color: legacy.color,
+        hobbies: legacy.hobbies,
+        is_private: false,
+    })
+}
+
+/// Reads favorites in the current layout. Unversioned accounts are shorter than
+/// `FAVORITES_SPACE` and would not deserialize, so they are told apart by size first.
+pub fn load_favorites(account: &AccountInfo) -> Result<Favorites> {
+    require_keys_eq!(*account.owner, crate::ID, anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram);
+    require!(account.data_len() >= FAVORITES_SPACE, ErrorCode::FavoritesNeedMigration);
+
+    let favorites = Favorites::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    require!(favorites.version == FAVORITES_VERSION, ErrorCode::FavoritesNeedMigration);
+
+    Ok(favorites)
+}
+
+fn store_favorites(account: &AccountInfo, favorites: &Favorites) -> Result<()> {
+    favorites.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+/// Creates the PDA signed for by `signer_seeds`, owned by this program. Like Anchor's
+/// `init`, an address someone already sent lamports to is topped up, allocated and
+/// assigned instead, since `create_account` refuses accounts with a balance.
+fn create_program_account<'info>(
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    account: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                &[signer_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let rent_due = rent.saturating_sub(account.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+/// Sends all of `account`'s lamports to `destination` and hands it back to the system program.
+fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    destination.add_lamports(account.lamports())?;
+    account.sub_lamports(account.lamports())?;
+    account.realloc(0, false)?;
+    account.assign(&system_program::ID);
+
+    Ok(())
+}
+
+/// Trims surrounding whitespace and puts the text in Unicode NFC, so the same
+/// visible string is always stored with the same bytes.
+pub fn normalize(value: &str) -> String {
+    value.trim().nfc().collect()
+}
+
+pub fn validate_color(color: &str) -> Result<String> {
+    let color = normalize(color);
+    require!(!color.is_empty(), ErrorCode::EmptyColor);
+    require!(color.len() <= MAX_COLOR_LENGTH, ErrorCode::ColorTooLong);
+
+    Ok(color)
+}
+
+pub fn validate_hobby(hobby: &str) -> Result<String> {
+    let hobby = normalize(hobby);
+    require!(!hobby.is_empty(), ErrorCode::EmptyHobby);
+    require!(hobby.len() <= MAX_HOBBY_LENGTH, ErrorCode::HobbyTooLong);
+
+    Ok(hobby)
+}
+
+pub fn validate_hobbies(hobbies: &[String]) -> Result<Vec<String>> {
+    require!(hobbies.len() <= MAX_HOBBIES, ErrorCode::TooManyHobbies);
+
+    let mut validated: Vec<String> = Vec::with_capacity(hobbies.len());
+    for hobby in hobbies {
+        let hobby = validate_hobby(hobby)?;
+        require!(!contains_hobby(&validated, &hobby), ErrorCode::DuplicateHobby);
+        validated.push(hobby);
+    }
+
+    Ok(validated)
+}
+
+/// Hobbies are compared ignoring case, so "Reading" and "reading" count as one.
+pub fn hobby_key(hobby: &str) -> String {
+    normalize(hobby).to_lowercase()
+}
+
+fn is_same_hobby(a: &str, b: &str) -> bool {
+    hobby_key(a) == hobby_key(b)
+}
+
+fn contains_hobby(hobbies: &[String], hobby: &str) -> bool {
+    hobbies.iter().any(|existing| is_same_hobby(existing, hobby))
+}
+
+pub fn find_hobby_index_address(hobby: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"hobby", hash(hobby_key(hobby).as_bytes()).as_ref()], &crate::ID)
+}
+
+/// Moves `user` out of the indexes of hobbies they dropped and into those of hobbies they added.
+fn sync_hobby_indexes<'info>(
+    user: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    old_hobbies: &[String],
+    new_hobbies: &[String],
+    index_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    for hobby in old_hobbies.iter().filter(|hobby| !contains_hobby(new_hobbies, hobby)) {
+        leave_hobby_index(user, hobby, index_accounts)?;
+    }
+    for hobby in new_hobbies.iter().filter(|hobby| !contains_hobby(old_hobbies, hobby)) {
+        join_hobby_index(user, system_program, hobby, index_accounts)?;
+    }
+
+    Ok(())
+}
+
+fn find_hobby_index<'a, 'info>(hobby: &str, index_accounts: &'a [AccountInfo<'info>]) -> Result<(&'a AccountInfo<'info>, u8)> {
+    let (address, bump) = find_hobby_index_address(hobby);
+    let account = index_accounts
+        .iter()
+        .find(|account| account.key() == address)
+        .ok_or(ErrorCode::HobbyIndexMissing)?;
+
+    Ok((account, bump))
+}
+
+fn join_hobby_index<'info>(
+    user: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    hobby: &str,
+    index_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let (account, bump) = find_hobby_index(hobby, index_accounts)?;
+
+    if account.data_is_empty() {
+        let index = HobbyIndex {
+            hobby: hobby_key(hobby),
+            users: vec![user.key()],
+        };
+        let hobby_hash = hash(index.hobby.as_bytes());
+        create_program_account(
+            user,
+            system_program,
+            account,
+            index.space(),
+            &[b"hobby", hobby_hash.as_ref(), &[bump]],
+        )?;
+
+        return index.try_serialize(&mut &mut account.try_borrow_mut_data()?[..]);
+    }
+
+    let mut index = load_hobby_index(account)?;
+    if index.users.len() >= MAX_HOBBY_INDEX_MEMBERS || index.users.contains(&user.key()) {
+        return Ok(());
+    }
+    index.users.push(user.key());
+
+    let rent_due = Rent::get()?.minimum_balance(index.space()).saturating_sub(account.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: user.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    account.realloc(index.space(), false)?;
+
+    index.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+/// Unmigrated hobbies and hobbies added to a full index were never indexed, so leaving
+/// an index that doesn't exist or doesn't list `user` does nothing.
+fn leave_hobby_index<'info>(user: &Signer<'info>, hobby: &str, index_accounts: &[AccountInfo<'info>]) -> Result<()> {
+    let (account, _) = find_hobby_index(hobby, index_accounts)?;
+    if account.data_is_empty() {
+        return Ok(());
+    }
+
+    let mut index = load_hobby_index(account)?;
+    let Some(position) = index.users.iter().position(|member| *member == user.key()) else {
+        return Ok(());
+    };
+    index.users.remove(position);
+
+    // The last member to leave gets all the rent back and the index is closed
+    if index.users.is_empty() {
+        return close_program_account(account, user);
+    }
+
+    account.realloc(index.space(), false)?;
+    let refund = account.lamports().saturating_sub(Rent::get()?.minimum_balance(index.space()));
+    account.sub_lamports(refund)?;
+    user.add_lamports(refund)?;
+
+    index.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+fn load_hobby_index(account: &AccountInfo) -> Result<HobbyIndex> {
+    require_keys_eq!(*account.owner, crate::ID, anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram);
+
+    HobbyIndex::try_deserialize(&mut &account.try_borrow_data()?[..])
 }
 
 #[derive(Accounts)]
@@ -59,26 +587,306 @@ pub struct SetFavorites<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
+    /// CHECK: created on first use, otherwise read through `load_favorites`
     #[account(
-        init_if_needed,
-        payer = user,
-        space = ANCHOR_DISCRIMINATOR_SIZE + Favorites::INIT_SPACE,
+        mut,
         seeds = [b"Favorite", user.key().as_ref()],
         bump
     )]
-    pub favorites: Account<'info, Favorites>,
+    pub favorites: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct GetFavorites<'info> {
+    pub user: Signer<'info>,
+
+    /// CHECK: read through `load_favorites`, which rejects unmigrated layouts
+    #[account(
+        seeds = [b"Favorite", user.key().as_ref()],
+        bump
+    )]
+    pub phrase: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct ViewFavorites<'info> {
+    /// CHECK: read through `load_favorites`, which rejects unmigrated layouts
+    #[account(
+        seeds = [b"Favorite", owner.as_ref()],
+        bump
+    )]
+    pub favorites: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFavorites<'info> {
+    pub user: Signer<'info>,
+
+    /// CHECK: read through `load_favorites`, which rejects unmigrated layouts
+    #[account(
+        mut,
+        seeds = [b"Favorite", user.key().as_ref()],
+        bump
+    )]
+    pub favorites: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateHobbies<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: read through `load_favorites`, which rejects unmigrated layouts
+    #[account(
+        mut,
+        seeds = [b"Favorite", user.key().as_ref()],
+        bump
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteFavorites<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
+    /// CHECK: may still use the unversioned layout, so the owner is checked here and
+    /// the contents when they are read
     #[account(
+        mut,
+        owner = crate::ID,
         seeds = [b"Favorite", user.key().as_ref()],
         bump
     )]
-    pub phrase: Account<'info, Favorites>,
+    pub favorites: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateFavorites<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: an unversioned account cannot be loaded as `Favorites`, so the owner is
+    /// checked here and the discriminator in `migrate_legacy_favorites`
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"Favorite", user.key().as_ref()],
+        bump
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(target: Pubkey)]
+pub struct Follow<'info> {
+    #[account(mut)]
+    pub follower: Signer<'info>,
+
+    #[account(
+        init,
+        payer = follower,
+        space = ANCHOR_DISCRIMINATOR_SIZE + FollowRecord::INIT_SPACE,
+        seeds = [b"follow", follower.key().as_ref(), target.as_ref()],
+        bump
+    )]
+    pub follow: Account<'info, FollowRecord>,
+
+    #[account(
+        init_if_needed,
+        payer = follower,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Profile::INIT_SPACE,
+        seeds = [b"profile", follower.key().as_ref()],
+        bump
+    )]
+    pub follower_profile: Account<'info, Profile>,
+
+    #[account(
+        init_if_needed,
+        payer = follower,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Profile::INIT_SPACE,
+        seeds = [b"profile", target.as_ref()],
+        bump
+    )]
+    pub target_profile: Account<'info, Profile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(target: Pubkey)]
+pub struct Unfollow<'info> {
+    #[account(mut)]
+    pub follower: Signer<'info>,
+
+    #[account(
+        mut,
+        close = follower,
+        seeds = [b"follow", follower.key().as_ref(), target.as_ref()],
+        bump
+    )]
+    pub follow: Account<'info, FollowRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", follower.key().as_ref()],
+        bump
+    )]
+    pub follower_profile: Account<'info, Profile>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", target.as_ref()],
+        bump
+    )]
+    pub target_profile: Account<'info, Profile>,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Color can be at most 50 bytes")]
+    ColorTooLong,
+    #[msg("Color cannot be empty")]
+    EmptyColor,
+    #[msg("Hobbies can be at most 50 bytes each")]
+    HobbyTooLong,
+    #[msg("Hobbies cannot be empty")]
+    EmptyHobby,
+    #[msg("The same hobby is listed more than once")]
+    DuplicateHobby,
+    #[msg("A user can have at most 5 hobbies")]
+    TooManyHobbies,
+    #[msg("Hobby is not in the user's favorites")]
+    HobbyNotFound,
+    #[msg("These favorites are private")]
+    FavoritesArePrivate,
+    #[msg("Favorites use an older layout; call migrate_favorites first")]
+    FavoritesNeedMigration,
+    #[msg("Favorites already use the current layout")]
+    AlreadyMigrated,
+    #[msg("Users cannot follow themselves")]
+    CannotFollowSelf,
+    #[msg("Counter overflow")]
+    CounterOverflow,
+    #[msg("The index account for a changed hobby was not passed in")]
+    HobbyIndexMissing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hobbies(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_and_normalizes_valid_favorites() {
+        assert_eq!(validate_color("  blue ").unwrap(), "blue");
+        // "e" followed by a combining acute accent composes to a single "é"
+        assert_eq!(validate_hobby("cafe\u{301}").unwrap(), "caf\u{e9}");
+        assert_eq!(
+            validate_hobbies(&hobbies(&["Reading", " cooking"])).unwrap(),
+            hobbies(&["Reading", "cooking"])
+        );
+    }
+
+    #[test]
+    fn migrates_legacy_layout() {
+        let legacy_space = ANCHOR_DISCRIMINATOR_SIZE + 8 + (4 + MAX_COLOR_LENGTH) + 4 + MAX_HOBBIES * (4 + MAX_HOBBY_LENGTH);
+        let mut data = Favorites::DISCRIMINATOR.to_vec();
+        (7u64, "blue".to_string(), hobbies(&["chess"])).serialize(&mut data).unwrap();
+        data.resize(legacy_space, 0);
+
+        let favorites = migrate_legacy_favorites(&data).unwrap();
+        assert_eq!(favorites.version, FAVORITES_VERSION);
+        assert_eq!(favorites.number, 7);
+        assert_eq!(favorites.color, "blue");
+        assert_eq!(favorites.hobbies, hobbies(&["chess"]));
+        assert!(!favorites.is_private);
+
+        data.resize(FAVORITES_SPACE, 0);
+        assert_eq!(migrate_legacy_favorites(&data).err(), Some(ErrorCode::AlreadyMigrated.into()));
+    }
+
+    #[test]
+    fn migration_ignores_bytes_after_the_legacy_fields() {
+        let mut data = Favorites::DISCRIMINATOR.to_vec();
+        (7u64, "blue".to_string(), hobbies(&["chess"])).serialize(&mut data).unwrap();
+        data.extend([1, 0xff, 0xff, 0xff]);
+
+        let favorites = migrate_legacy_favorites(&data).unwrap();
+        assert_eq!(favorites.hobbies, hobbies(&["chess"]));
+        assert!(!favorites.is_private);
+    }
+
+    #[test]
+    fn loading_unmigrated_favorites_asks_for_migration() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = Favorites::DISCRIMINATOR.to_vec();
+        (7u64, "blue".to_string(), hobbies(&["chess"])).serialize(&mut data).unwrap();
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+
+        assert_eq!(load_favorites(&account).err(), Some(ErrorCode::FavoritesNeedMigration.into()));
+    }
+
+    #[test]
+    fn hobby_index_address_ignores_case_and_spacing() {
+        assert_eq!(find_hobby_index_address(" Chess"), find_hobby_index_address("chess"));
+        assert_ne!(find_hobby_index_address("chess"), find_hobby_index_address("checkers"));
+    }
+
+    #[test]
+    fn rejects_empty_color() {
+        assert_eq!(validate_color("").unwrap_err(), ErrorCode::EmptyColor.into());
+        assert_eq!(validate_color("   ").unwrap_err(), ErrorCode::EmptyColor.into());
+    }
+
+    #[test]
+    fn rejects_long_color() {
+        assert!(validate_color(&"a".repeat(MAX_COLOR_LENGTH)).is_ok());
+        assert_eq!(
+            validate_color(&"a".repeat(MAX_COLOR_LENGTH + 1)).unwrap_err(),
+            ErrorCode::ColorTooLong.into()
+        );
+    }
+
+    #[test]
+    fn rejects_empty_hobby() {
+        assert_eq!(validate_hobbies(&hobbies(&["reading", " "])).unwrap_err(), ErrorCode::EmptyHobby.into());
+    }
+
+    #[test]
+    fn rejects_long_hobby() {
+        assert_eq!(
+            validate_hobbies(&hobbies(&[&"a".repeat(MAX_HOBBY_LENGTH + 1)])).unwrap_err(),
+            ErrorCode::HobbyTooLong.into()
+        );
+    }
+
+    #[test]
+    fn rejects_too_many_hobbies() {
+        assert!(validate_hobbies(&hobbies(&["a", "b", "c", "d", "e"])).is_ok());
+        assert_eq!(
+            validate_hobbies(&hobbies(&["a", "b", "c", "d", "e", "f"])).unwrap_err(),
+            ErrorCode::TooManyHobbies.into()
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_hobbies() {
+        assert_eq!(validate_hobbies(&hobbies(&["reading", "reading"])).unwrap_err(), ErrorCode::DuplicateHobby.into());
+        assert_eq!(validate_hobbies(&hobbies(&["Reading", "reading "])).unwrap_err(), ErrorCode::DuplicateHobby.into());
+        assert_eq!(validate_hobbies(&hobbies(&["caf\u{e9}", "cafe\u{301}"])).unwrap_err(), ErrorCode::DuplicateHobby.into());
+    }
 }
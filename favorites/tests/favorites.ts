@@ -2,6 +2,7 @@ import * as anchor from '@coral-xyz/anchor';
 import type { Program } from '@coral-xyz/anchor';
 import { getCustomErrorMessage } from '@solana-developers/helpers';
 import { assert } from 'chai';
+import { createHash } from 'node:crypto';
 import type { Favorites } from '../target/types/favorites';
 import { systemProgramErrors } from './system-errors';
 const web3 = anchor.web3;
@@ -19,6 +20,15 @@ describe('Favorites', () => {
   const favoriteColor = 'blue';
   const favoriteHobbies = ['Reading', 'cooking', 'driving'];
 
+  // Hobby index PDAs are keyed by the sha256 of the trimmed, NFC-normalized, lowercased hobby
+  const hobbyIndexPda = (hobby: string) =>
+    web3.PublicKey.findProgramAddressSync(
+      [Buffer.from('hobby'), createHash('sha256').update(hobby.trim().normalize('NFC').toLowerCase()).digest()],
+      program.programId,
+    )[0];
+  const hobbyIndexAccounts = (hobbies: string[]) =>
+    [...new Set(hobbies)].map((hobby) => ({ pubkey: hobbyIndexPda(hobby), isSigner: false, isWritable: true }));
+
   // We don't need to airdrop if we're using the local cluster
   // because the local cluster gives us 85 billion dollars worth of SOL
   before(async () => {
@@ -32,6 +42,8 @@ describe('Favorites', () => {
     await program.methods
       // set_favourites in Rust becomes setFavorites in TypeScript
       .setFavorites(favoriteNumber, favoriteColor, favoriteHobbies)
+      // Every hobby that is added or removed needs its index account
+      .remainingAccounts(hobbyIndexAccounts(favoriteHobbies))
       // Sign the transaction
       .signers([user])
       // Send the transaction to the cluster or RPC
@@ -49,37 +61,28 @@ describe('Favorites', () => {
     assert.deepEqual(dataFromPda.hobbies, favoriteHobbies);
   });
 
-  it('Invokes the get_favorites function on the blockchain', async () => {
-    // Find the PDA for the user's favorites
-    const favoritesPdaAndBump = web3.PublicKey.findProgramAddressSync([Buffer.from('Favorite'), user.publicKey.toBuffer()], program.programId);
-    const favoritesPda = favoritesPdaAndBump[0];
-  
-    // Call the get_favorites instruction from the program
-    try {
-      await program.methods
-        .getFavorites()  // This matches the `pub fn get_favorites` in Rust
-        .accounts({
-          user: user.publicKey,     // User's public key 
-        })
-        .rpc();
-  
-      // Fetch the account data again to validate
-      const favoritesData = await program.account.favorites.fetch(favoritesPda);
-  
-      // Validate the fetched data matches the expected values
-      assert.equal(favoritesData.color, favoriteColor);
-      assert.equal(favoritesData.number.toString(), favoriteNumber.toString());
-      assert.deepEqual(favoritesData.hobbies, favoriteHobbies);
-    } catch (error) {
-      console.error('Error invoking getFavorites:', error);
-    }
+  it('Reads our favorites back from get_favorites', async () => {
+    // get_favorites returns the account through return data, which view() reads from a simulation
+    const favoritesData = await program.methods
+      .getFavorites() // This matches the `pub fn get_favorites` in Rust
+      .accounts({
+        user: user.publicKey, // User's public key
+      })
+      .view();
+
+    assert.equal(favoritesData.color, favoriteColor);
+    assert.equal(favoritesData.number.toString(), favoriteNumber.toString());
+    assert.deepEqual(favoritesData.hobbies, favoriteHobbies);
   });
-  
 
   it('Updates the favorites', async () => {
     const newFavoriteHobbies = ['skiing', 'skydiving', 'biking', 'swimming'];
     try {
-      await program.methods.setFavorites(favoriteNumber, favoriteColor, newFavoriteHobbies).signers([user]).rpc();
+      await program.methods
+        .setFavorites(favoriteNumber, favoriteColor, newFavoriteHobbies)
+        .remainingAccounts(hobbyIndexAccounts([...favoriteHobbies, ...newFavoriteHobbies]))
+        .signers([user])
+        .rpc();
     } catch (error) {
       console.error((error as Error).message);
       const customErrorMessage = getCustomErrorMessage(systemProgramErrors, error);
@@ -101,4 +104,113 @@ describe('Favorites', () => {
       assert.isTrue(errorMessage.includes('unknown signer'));
     }
   });
+
+  it('Edits individual fields', async () => {
+    const favoritesPda = web3.PublicKey.findProgramAddressSync([Buffer.from('Favorite'), user.publicKey.toBuffer()], program.programId)[0];
+
+    await program.methods.updateNumber(new anchor.BN(7)).accounts({ user: user.publicKey }).rpc();
+    await program.methods.updateColor('green').accounts({ user: user.publicKey }).rpc();
+    await program.methods.addHobby('climbing').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['climbing'])).rpc();
+    await program.methods.removeHobby('skiing').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['skiing'])).rpc();
+
+    const favoritesData = await program.account.favorites.fetch(favoritesPda);
+    assert.equal(favoritesData.number.toString(), '7');
+    assert.equal(favoritesData.color, 'green');
+    assert.deepEqual(favoritesData.hobbies, ['skydiving', 'biking', 'swimming', 'climbing']);
+  });
+
+  it('Indexes users by hobby', async () => {
+    const biking = await program.account.hobbyIndex.fetch(hobbyIndexPda('Biking'));
+    assert.equal(biking.hobby, 'biking');
+    assert.isTrue(biking.users.some((member) => member.equals(user.publicKey)));
+
+    // Dropped hobbies with no other members have their index closed
+    assert.isNull(await provider.connection.getAccountInfo(hobbyIndexPda('skiing')));
+    assert.isNull(await provider.connection.getAccountInfo(hobbyIndexPda('Reading')));
+  });
+
+  it('Creates a hobby index that already holds lamports', async () => {
+    // Anyone can send SOL to an index address before the index exists
+    const potteryPda = hobbyIndexPda('pottery');
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(web3.SystemProgram.transfer({ fromPubkey: user.publicKey, toPubkey: potteryPda, lamports: rent })),
+    );
+
+    await program.methods.addHobby('pottery').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['pottery'])).rpc();
+    const pottery = await program.account.hobbyIndex.fetch(potteryPda);
+    assert.isTrue(pottery.users.some((member) => member.equals(user.publicKey)));
+
+    await program.methods.removeHobby('pottery').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['pottery'])).rpc();
+    assert.isNull(await provider.connection.getAccountInfo(potteryPda));
+  });
+
+  it('Rejects a sixth hobby', async () => {
+    await program.methods.addHobby('running').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['running'])).rpc();
+    try {
+      await program.methods.addHobby('rowing').accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(['rowing'])).rpc();
+      assert.fail('sixth hobby was accepted');
+    } catch (error) {
+      assert.isTrue((error as Error).message.includes('TooManyHobbies'));
+    }
+  });
+
+  it('Lets anyone view public favorites but not private ones', async () => {
+    const favoritesData = await program.methods.viewFavorites(user.publicKey).view();
+    assert.equal(favoritesData.color, 'green');
+
+    await program.methods.setVisibility(true).accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(favoritesData.hobbies)).rpc();
+    try {
+      await program.methods.viewFavorites(user.publicKey).view();
+      assert.fail('private favorites were returned');
+    } catch (error) {
+      assert.isTrue((error as Error).message.includes('FavoritesArePrivate'));
+    }
+  });
+
+  it('Keeps private users out of the hobby indexes', async () => {
+    // Going private closed the indexes this user was the only member of
+    assert.isNull(await provider.connection.getAccountInfo(hobbyIndexPda('biking')));
+
+    // Hobbies added while private are not indexed either
+    await program.methods.removeHobby('running').accounts({ user: user.publicKey }).rpc();
+    await program.methods.addHobby('rowing').accounts({ user: user.publicKey }).rpc();
+    assert.isNull(await provider.connection.getAccountInfo(hobbyIndexPda('rowing')));
+
+    const hobbies = ['skydiving', 'biking', 'swimming', 'climbing', 'rowing'];
+    await program.methods.setVisibility(false).accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(hobbies)).rpc();
+    for (const hobby of hobbies) {
+      const index = await program.account.hobbyIndex.fetch(hobbyIndexPda(hobby));
+      assert.isTrue(index.users.some((member) => member.equals(user.publicKey)));
+    }
+  });
+
+  it('Deletes the favorites and refunds rent', async () => {
+    const favoritesPda = web3.PublicKey.findProgramAddressSync([Buffer.from('Favorite'), user.publicKey.toBuffer()], program.programId)[0];
+
+    const hobbies = (await program.account.favorites.fetch(favoritesPda)).hobbies;
+    await program.methods.deleteFavorites().accounts({ user: user.publicKey }).remainingAccounts(hobbyIndexAccounts(hobbies)).rpc();
+
+    const accountInfo = await provider.connection.getAccountInfo(favoritesPda);
+    assert.isNull(accountInfo);
+  });
+
+  it('Follows and unfollows another user', async () => {
+    const target = someRandomGuy.publicKey;
+    const [followerProfilePda] = web3.PublicKey.findProgramAddressSync([Buffer.from('profile'), user.publicKey.toBuffer()], program.programId);
+    const [targetProfilePda] = web3.PublicKey.findProgramAddressSync([Buffer.from('profile'), target.toBuffer()], program.programId);
+    const [followPda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from('follow'), user.publicKey.toBuffer(), target.toBuffer()],
+      program.programId,
+    );
+
+    await program.methods.follow(target).accounts({ follower: user.publicKey }).rpc();
+    assert.equal((await program.account.profile.fetch(followerProfilePda)).followingCount.toString(), '1');
+    assert.equal((await program.account.profile.fetch(targetProfilePda)).followerCount.toString(), '1');
+
+    await program.methods.unfollow(target).accounts({ follower: user.publicKey }).rpc();
+    assert.equal((await program.account.profile.fetch(followerProfilePda)).followingCount.toString(), '0');
+    assert.equal((await program.account.profile.fetch(targetProfilePda)).followerCount.toString(), '0');
+    assert.isNull(await provider.connection.getAccountInfo(followPda));
+  });
 });
\ No newline at end of file
@@ -4,3 +4,19 @@ use anchor_lang::prelude::*;
 pub const SEED: &str = "anchor";
 
 pub const ANCHOR_DISCRIMINATOR: usize =8;
+
+pub const BASIS_POINTS_DIVISOR: u16 = 10_000;
+
+#[constant]
+pub const MAX_FEE_BASIS_POINTS: u16 = 1_000;
+
+#[constant]
+pub const MAX_BASKET_LEGS: usize = 3;
+
+#[constant]
+pub const MAX_ORDER_BOOK_ENTRIES: usize = 64;
+
+/// Stored as the mint of an offer leg that is paid in native SOL. Not a mint account,
+/// and unrelated to SPL's wrapped SOL mint.
+#[constant]
+pub const NATIVE_SOL_SENTINEL: Pubkey = Pubkey::new_from_array([0; 32]);
\ No newline at end of file
@@ -0,0 +1,162 @@
+//! Helpers for programs that compose swaps through CPI and for clients building
+//! transactions.
+//!
+//! The address helpers are always available. The CPI wrappers need the `cpi` feature:
+//!
+//! ```toml
+//! swap = { path = "../swap", features = ["cpi"] }
+//! ```
+//!
+//! They cover token-for-token offers. Native SOL legs and transfer-hook accounts go
+//! through the generated `swap::cpi` module directly.
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+/// Offer PDA for `maker`'s offer `id`, seeded by `[b"offer", maker, id]`.
+pub fn find_offer_address(maker: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer", maker.as_ref(), &id.to_le_bytes()], &crate::ID)
+}
+
+/// Basket offer PDA for `maker`'s basket offer `id`.
+pub fn find_basket_offer_address(maker: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"basket_offer", maker.as_ref(), &id.to_le_bytes()], &crate::ID)
+}
+
+/// The program-wide fee config PDA.
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &crate::ID)
+}
+
+/// Order book PDA for a pair. Use `NATIVE_SOL_SENTINEL` for a native SOL leg.
+pub fn find_order_book_address(token_mint_a: &Pubkey, token_mint_b: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"order_book", token_mint_a.as_ref(), token_mint_b.as_ref()],
+        &crate::ID,
+    )
+}
+
+/// Vault holding the offered token A, the offer's associated token account.
+pub fn get_vault_address(offer: &Pubkey, token_mint_a: &Pubkey, token_program_a: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(offer, token_mint_a, token_program_a)
+}
+
+#[cfg(feature = "cpi")]
+pub use cpi_wrappers::*;
+
+#[cfg(feature = "cpi")]
+mod cpi_wrappers {
+    use anchor_lang::prelude::*;
+
+    use crate::cpi::{self, accounts};
+
+    /// Accounts for [`make_offer_cpi`]. `maker` must sign, either as a signer of the
+    /// outer transaction or through `signer_seeds` when it is a PDA of the caller.
+    pub struct MakeOfferCpiAccounts<'info> {
+        pub swap_program: AccountInfo<'info>,
+        pub maker: AccountInfo<'info>,
+        pub token_mint_a: AccountInfo<'info>,
+        pub token_mint_b: AccountInfo<'info>,
+        pub maker_token_account_a: AccountInfo<'info>,
+        pub offer: AccountInfo<'info>,
+        pub vault: AccountInfo<'info>,
+        pub order_book: AccountInfo<'info>,
+        pub system_program: AccountInfo<'info>,
+        pub token_program_a: AccountInfo<'info>,
+        pub token_program_b: AccountInfo<'info>,
+        pub associated_token_program: AccountInfo<'info>,
+    }
+
+    /// Accounts for [`take_offer_cpi`]. `taker` must sign like `maker` above.
+    pub struct TakeOfferCpiAccounts<'info> {
+        pub swap_program: AccountInfo<'info>,
+        pub taker: AccountInfo<'info>,
+        pub maker: AccountInfo<'info>,
+        pub taker_token_account_a: AccountInfo<'info>,
+        pub taker_token_account_b: AccountInfo<'info>,
+        pub maker_token_account_b: AccountInfo<'info>,
+        pub config: AccountInfo<'info>,
+        pub fee_recipient: AccountInfo<'info>,
+        pub fee_token_account_b: AccountInfo<'info>,
+        pub offer: AccountInfo<'info>,
+        pub vault: AccountInfo<'info>,
+        pub order_book: AccountInfo<'info>,
+        pub token_mint_a: AccountInfo<'info>,
+        pub token_mint_b: AccountInfo<'info>,
+        pub system_program: AccountInfo<'info>,
+        pub token_program_a: AccountInfo<'info>,
+        pub token_program_b: AccountInfo<'info>,
+        pub associated_token_program: AccountInfo<'info>,
+    }
+
+    /// Opens a token-for-token offer.
+    pub fn make_offer_cpi<'info>(
+        accounts: MakeOfferCpiAccounts<'info>,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        expires_at: i64,
+        allowed_taker: Option<Pubkey>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_context = CpiContext::new_with_signer(
+            accounts.swap_program,
+            accounts::MakeOffer {
+                maker: accounts.maker,
+                token_mint_a: Some(accounts.token_mint_a),
+                token_mint_b: Some(accounts.token_mint_b),
+                maker_token_account_a: Some(accounts.maker_token_account_a),
+                offer: accounts.offer,
+                vault: Some(accounts.vault),
+                order_book: accounts.order_book,
+                system_program: accounts.system_program,
+                token_program_a: Some(accounts.token_program_a),
+                token_program_b: Some(accounts.token_program_b),
+                associated_token_program: accounts.associated_token_program,
+            },
+            signer_seeds,
+        );
+
+        cpi::make_offer(
+            cpi_context,
+            id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            expires_at,
+            allowed_taker,
+        )
+    }
+
+    /// Fills `token_b_amount` of a token-for-token offer.
+    pub fn take_offer_cpi<'info>(
+        accounts: TakeOfferCpiAccounts<'info>,
+        token_b_amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_context = CpiContext::new_with_signer(
+            accounts.swap_program,
+            accounts::TakeOffer {
+                taker: accounts.taker,
+                maker: accounts.maker,
+                taker_token_account_a: Some(accounts.taker_token_account_a),
+                taker_token_account_b: Some(accounts.taker_token_account_b),
+                maker_token_account_b: Some(accounts.maker_token_account_b),
+                config: accounts.config,
+                fee_recipient: accounts.fee_recipient,
+                fee_token_account_b: Some(accounts.fee_token_account_b),
+                offer: accounts.offer,
+                vault: Some(accounts.vault),
+                order_book: accounts.order_book,
+                token_mint_a: Some(accounts.token_mint_a),
+                token_mint_b: Some(accounts.token_mint_b),
+                system_program: accounts.system_program,
+                token_program_a: Some(accounts.token_program_a),
+                token_program_b: Some(accounts.token_program_b),
+                associated_token_program: accounts.associated_token_program,
+            },
+            signer_seeds,
+        );
+
+        cpi::take_offer(cpi_context, token_b_amount)
+    }
+}
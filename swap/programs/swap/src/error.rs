@@ -2,6 +2,50 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Custom error message")]
-    CustomError,
+    #[msg("Offered and wanted amounts must be greater than zero")]
+    InvalidAmount,
+    #[msg("Fill amount must be greater than zero and no more than the remaining amount")]
+    InvalidFillAmount,
+    #[msg("Fill amount is too small to release any offered tokens")]
+    FillTooSmall,
+    #[msg("Calculation overflow")]
+    CalculationOverflow,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Offer has not expired yet")]
+    OfferNotExpired,
+    #[msg("Offer is reserved for a different taker")]
+    TakerNotAllowed,
+    #[msg("Fee exceeds the maximum allowed basis points")]
+    FeeTooHigh,
+    #[msg("Only the config admin can perform this action")]
+    Unauthorized,
+    #[msg("Mint is not owned by the supplied token program")]
+    TokenProgramMismatch,
+    #[msg("Token leg is missing its mint, token accounts or token program")]
+    IncompleteTokenAccounts,
+    #[msg("At least one leg of an offer must be a token")]
+    NativeOnBothLegs,
+    #[msg("Basket legs must be non-empty, within the leg limit, non-zero and use unique mints")]
+    InvalidBasketLegs,
+    #[msg("Remaining accounts do not match the basket legs")]
+    BasketAccountsMismatch,
+    #[msg("Order book is full and the offer is not better than its worst entry")]
+    OrderBookFull,
+    #[msg("Order book does not belong to the offer's token pair")]
+    OrderBookMismatch,
+    #[msg("Remaining accounts do not match the offers being taken")]
+    MarketAccountsMismatch,
+    #[msg("An offer is priced worse than the taker's worst price")]
+    WorstPriceExceeded,
+    #[msg("Offers must be supplied from best to worst price")]
+    OffersNotSorted,
+    #[msg("No offer could be filled")]
+    NoOffersFilled,
+    #[msg("Withdrawal must leave some token A on offer; refund the offer to take it all back")]
+    WithdrawalTooLarge,
+    #[msg("Fee recipient must hold the rent-exempt minimum to receive native SOL fees")]
+    FeeRecipientNotRentExempt,
 }
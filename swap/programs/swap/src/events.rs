@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+
+use crate::BasketLeg;
+
+#[event]
+pub struct OfferMade {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub expires_at: i64,
+    pub allowed_taker: Option<Pubkey>,
+}
+
+#[event]
+pub struct OfferTaken {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub fee: u64,
+    pub remaining_a: u64,
+    pub remaining_b: u64,
+}
+
+#[event]
+pub struct OfferCancelled {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_a_refunded_amount: u64,
+}
+
+#[event]
+pub struct OfferUpdated {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_a_deposited_amount: u64,
+    pub token_a_withdrawn_amount: u64,
+    pub remaining_a: u64,
+    pub remaining_b: u64,
+}
+
+#[event]
+pub struct ExpiredOfferCranked {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub cranker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_a_refunded_amount: u64,
+}
+
+#[event]
+pub struct BasketOfferMade {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+    pub wanted: Vec<BasketLeg>,
+}
+
+#[event]
+pub struct BasketOfferTaken {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+    pub wanted: Vec<BasketLeg>,
+}
+
+#[event]
+pub struct BasketOfferCancelled {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface}
+};
+
+use crate::{error::ErrorCode, events::ExpiredOfferCranked, Offer, NATIVE_SOL_SENTINEL};
+
+use super::{close_vault, update_order_book_if_initialized, withdraw_from_vault};
+
+/// Anyone may crank an expired offer. The maker gets the remaining tokens and the
+/// offer rent back, the cranker keeps the vault rent as a bounty. Native SOL offers
+/// have no vault, so everything goes back to the maker.
+#[derive(Accounts)]
+pub struct CrankExpiredOffer<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(mut, mint::token_program = token_program)]
+    pub token_mint_a: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        constraint = offer.token_mint_a == token_mint_a.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        constraint = offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferNotExpired,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the pair's order book, which may not be initialized yet
+    #[account(
+        mut,
+        seeds = [b"order_book", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump
+    )]
+    pub order_book: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+
+}
+
+pub fn refund_expired_and_close_vault<'info>(context: Context<'_, '_, '_, 'info, CrankExpiredOffer<'info>>) -> Result<()> {
+    let accounts = &context.accounts;
+
+    update_order_book_if_initialized(&accounts.order_book, |order_book| order_book.remove(&accounts.offer.key()))?;
+
+    emit!(ExpiredOfferCranked {
+        id: accounts.offer.id,
+        maker: accounts.offer.maker,
+        cranker: accounts.cranker.key(),
+        token_mint_a: accounts.offer.token_mint_a,
+        token_a_refunded_amount: accounts.offer.remaining_a,
+    });
+
+    // Native SOL stays in the offer account and is returned when it closes
+    if accounts.offer.is_native_a() {
+        return Ok(());
+    }
+
+    let (Some(maker_token_account_a), Some(vault), Some(token_mint_a), Some(token_program)) = (
+        &accounts.maker_token_account_a,
+        &accounts.vault,
+        &accounts.token_mint_a,
+        &accounts.token_program,
+    ) else {
+        return err!(ErrorCode::IncompleteTokenAccounts);
+    };
+
+    withdraw_from_vault(
+        &accounts.offer,
+        vault,
+        maker_token_account_a,
+        &vault.amount,
+        token_mint_a,
+        token_program,
+        context.remaining_accounts,
+    )?;
+
+    close_vault(
+        &accounts.offer,
+        vault,
+        token_mint_a,
+        &accounts.cranker.to_account_info(),
+        token_program,
+    )
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, ANCHOR_DISCRIMINATOR, MAX_FEE_BASIS_POINTS};
+
+use super::is_rent_exempt;
+
+/// Only the program's upgrade authority may create the config, so nobody can
+/// front-run the deployment and claim the admin role.
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Swap>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    /// CHECK: only its balance is read, see `Config::fee_recipient`
+    #[account(constraint = is_rent_exempt(&fee_recipient)? @ ErrorCode::FeeRecipientNotRentExempt)]
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn save_config(ctx: Context<InitializeConfig>, fee_basis_points: u16) -> Result<()> {
+
+    require!(fee_basis_points <= MAX_FEE_BASIS_POINTS, ErrorCode::FeeTooHigh);
+
+    ctx.accounts.config.set_inner(Config {
+        admin: ctx.accounts.admin.key(),
+        fee_basis_points,
+        fee_recipient: ctx.accounts.fee_recipient.key(),
+        bump: ctx.bumps.config,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::{OrderBook, ANCHOR_DISCRIMINATOR};
+
+/// Anyone can open the order book for a pair. Use `NATIVE_SOL_SENTINEL` for a native SOL leg.
+#[derive(Accounts)]
+#[instruction(token_mint_a: Pubkey, token_mint_b: Pubkey)]
+pub struct InitializeOrderBook<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = ANCHOR_DISCRIMINATOR + std::mem::size_of::<OrderBook>(),
+        seeds = [b"order_book", token_mint_a.as_ref(), token_mint_b.as_ref()],
+        bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn save_order_book(ctx: Context<InitializeOrderBook>, token_mint_a: Pubkey, token_mint_b: Pubkey) -> Result<()> {
+
+    let mut order_book = ctx.accounts.order_book.load_init()?;
+    order_book.token_mint_a = token_mint_a;
+    order_book.token_mint_b = token_mint_b;
+    order_book.bump = ctx.bumps.order_book;
+
+    Ok(())
+}
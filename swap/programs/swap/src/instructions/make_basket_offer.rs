@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create, AssociatedToken, Create},
+    token_interface::{Mint, TokenAccount, TokenInterface}
+};
+
+use crate::{error::ErrorCode, events::BasketOfferMade, BasketLeg, BasketOffer, ANCHOR_DISCRIMINATOR};
+
+use super::{transfer_tokens, BASKET_OFFERED_LEG_ACCOUNTS};
+
+/// Offered legs are passed as remaining accounts, `[mint, vault, maker token account, token program]`
+/// per leg in the same order as `token_a_offered_amounts`. Any accounts after them are forwarded
+/// to transfer hooks.
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct MakeBasketOffer<'info> {
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + BasketOffer::INIT_SPACE,
+        seeds = [b"basket_offer", maker.key().as_ref(), id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub basket_offer: Account<'info, BasketOffer>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+
+}
+
+pub fn send_offered_tokens_to_vaults<'info>(ctx: &Context<'_, '_, 'info, 'info, MakeBasketOffer<'info>>, 
+                                           token_a_offered_amounts: &[u64]) -> Result<Vec<BasketLeg>> {
+
+    let leg_accounts_len = token_a_offered_amounts.len() * BASKET_OFFERED_LEG_ACCOUNTS;
+    require!(ctx.remaining_accounts.len() >= leg_accounts_len, ErrorCode::BasketAccountsMismatch);
+    let (leg_accounts, hook_accounts) = ctx.remaining_accounts.split_at(leg_accounts_len);
+
+    let mut offered = Vec::with_capacity(token_a_offered_amounts.len());
+    for (amount, accounts) in token_a_offered_amounts.iter().zip(leg_accounts.chunks(BASKET_OFFERED_LEG_ACCOUNTS)) {
+        let mint = InterfaceAccount::<Mint>::try_from(&accounts[0])?;
+        let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+        let token_program = Interface::<TokenInterface>::try_from(&accounts[3])?;
+
+        // Creating (rather than reusing) the vault guarantees it starts out empty
+        create(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            Create {
+                payer: ctx.accounts.maker.to_account_info(),
+                associated_token: accounts[1].clone(),
+                authority: ctx.accounts.basket_offer.to_account_info(),
+                mint: mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))?;
+        let mut vault = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+
+        transfer_tokens(
+            &maker_token_account,
+            &vault,
+            amount,
+            &mint,
+            &ctx.accounts.maker,
+            &token_program,
+            hook_accounts,
+        )?;
+
+        // Record what actually arrived after any transfer fee
+        vault.reload()?;
+        offered.push(BasketLeg {
+            mint: mint.key(),
+            amount: vault.amount,
+        });
+    }
+
+    Ok(offered)
+}
+
+pub fn save_basket_offer(ctx: Context<MakeBasketOffer>, 
+                         id: u64, 
+                         offered: Vec<BasketLeg>, 
+                         wanted: Vec<BasketLeg>) -> Result<()> {
+
+    require!(BasketOffer::validate_legs(&offered), ErrorCode::InvalidBasketLegs);
+    require!(BasketOffer::validate_legs(&wanted), ErrorCode::InvalidBasketLegs);
+
+    emit!(BasketOfferMade {
+        id,
+        maker: ctx.accounts.maker.key(),
+        offered: offered.clone(),
+        wanted: wanted.clone(),
+    });
+
+    ctx.accounts.basket_offer.set_inner(BasketOffer {
+        id,
+        maker: ctx.accounts.maker.key(),
+        offered,
+        wanted,
+        bump: ctx.bumps.basket_offer,
+    });
+
+    Ok(())
+}
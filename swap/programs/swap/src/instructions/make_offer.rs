@@ -4,10 +4,12 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount, TokenInterface}
 };
 
-use crate::{Offer, ANCHOR_DISCRIMINATOR};
+use crate::{error::ErrorCode, events::OfferMade, Offer, OrderBookEntry, ANCHOR_DISCRIMINATOR, NATIVE_SOL_SENTINEL};
 
-use super::transfer_tokens;
+use super::{transfer_lamports, transfer_tokens, update_order_book_if_initialized};
 
+/// Leaving out `token_mint_a` (and its token accounts) offers native SOL, which is
+/// escrowed in the `offer` account itself. Leaving out `token_mint_b` asks for SOL.
 #[derive(Accounts)]
 #[instruction(id: u64)]
 pub struct MakeOffer<'info> {
@@ -15,19 +17,19 @@ pub struct MakeOffer<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
 
-    #[account(mint::token_program = token_program)]
-    pub token_mint_a: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_a)]
+    pub token_mint_a: Option<InterfaceAccount<'info, Mint>>,
 
-    #[account(mint::token_program = token_program)]
-    pub token_mint_b: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_b)]
+    pub token_mint_b: Option<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
         associated_token::mint = token_mint_a,
         associated_token::authority = maker,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_a
     )]
-    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_token_account_a: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init,
@@ -43,38 +45,119 @@ pub struct MakeOffer<'info> {
         payer = maker,
         associated_token::mint = token_mint_a,
         associated_token::authority= offer,
-        associated_token::token_program = token_program
+        associated_token::token_program = token_program_a
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the pair's order book, which may not be initialized yet
+    #[account(
+        mut,
+        seeds = [
+            b"order_book",
+            token_mint_a.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()).as_ref(),
+            token_mint_b.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()).as_ref(),
+        ],
+        bump
+    )]
+    pub order_book: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_a: Option<Interface<'info, TokenInterface>>,
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
     pub associated_token_program: Program<'info, AssociatedToken>
 
 }
 
-pub fn send_offered_tokens_to_vault(ctx: &Context<MakeOffer>, token_a_offered_amount: u64) -> Result<()> {
-
-    transfer_tokens(
-        &ctx.accounts.maker_token_account_a,
-        &ctx.accounts.vault, 
-        &token_a_offered_amount, 
-        &ctx.accounts.token_mint_a, 
-        &ctx.accounts.maker,
-        &ctx.accounts.token_program,
-    )
+pub fn send_offered_tokens_to_vault<'info>(ctx: &Context<'_, '_, '_, 'info, MakeOffer<'info>>, token_a_offered_amount: u64) -> Result<()> {
+
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidAmount);
+
+    let accounts = &ctx.accounts;
+    match (&accounts.token_mint_a, &accounts.maker_token_account_a, &accounts.vault, &accounts.token_program_a) {
+        (Some(token_mint_a), Some(maker_token_account_a), Some(vault), Some(token_program_a)) => transfer_tokens(
+            maker_token_account_a,
+            vault, 
+            &token_a_offered_amount, 
+            token_mint_a, 
+            &accounts.maker,
+            token_program_a,
+            ctx.remaining_accounts,
+        ),
+        (None, None, None, None) => transfer_lamports(
+            &accounts.maker,
+            &accounts.offer.to_account_info(),
+            &token_a_offered_amount,
+            &accounts.system_program,
+        ),
+        _ => err!(ErrorCode::IncompleteTokenAccounts),
+    }
 }
 
-pub fn save_offer(ctx: Context<MakeOffer>, id: u64, token_b_wanted_amount: u64) -> Result<()>{
+pub fn save_offer(ctx: Context<MakeOffer>, 
+                  id: u64, 
+                  token_a_offered_amount: u64, 
+                  token_b_wanted_amount: u64, 
+                  expires_at: i64,
+                  allowed_taker: Option<Pubkey>) -> Result<()>{
+
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+    require!(expires_at > Clock::get()?.unix_timestamp, ErrorCode::InvalidExpiry);
+    require!(
+        ctx.accounts.token_mint_b.is_some() == ctx.accounts.token_program_b.is_some(),
+        ErrorCode::IncompleteTokenAccounts
+    );
+    require!(
+        ctx.accounts.token_mint_a.is_some() || ctx.accounts.token_mint_b.is_some(),
+        ErrorCode::NativeOnBothLegs
+    );
+
+    // The vault is fresh, so its balance is what actually arrived after any transfer fee
+    let token_a_offered_amount = match &mut ctx.accounts.vault {
+        Some(vault) => {
+            vault.reload()?;
+            vault.amount
+        }
+        None => token_a_offered_amount,
+    };
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidAmount);
 
     ctx.accounts.offer.set_inner(Offer{
         id,
         maker: ctx.accounts.maker.key(),
-        token_mint_a: ctx.accounts.token_mint_a.key(),
-        token_mint_b: ctx.accounts.token_mint_b.key(),
+        token_mint_a: ctx.accounts.token_mint_a.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        token_mint_b: ctx.accounts.token_mint_b.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        token_a_offered_amount,
         token_b_wanted_amount,
+        remaining_a: token_a_offered_amount,
+        remaining_b: token_b_wanted_amount,
+        expires_at,
+        allowed_taker,
         bump: ctx.bumps.offer,
     });
 
+    // Private offers, offers that don't fit in a full book, and offers whose pair has no
+    // book yet stay unlisted
+    let offer = &ctx.accounts.offer;
+    if offer.allowed_taker.is_none() {
+        update_order_book_if_initialized(&ctx.accounts.order_book, |order_book| {
+            order_book.insert(OrderBookEntry {
+                offer: offer.key(),
+                remaining_a: offer.remaining_a,
+                remaining_b: offer.remaining_b,
+            });
+        })?;
+    }
+
+    emit!(OfferMade {
+        id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        expires_at,
+        allowed_taker,
+    });
+
     Ok(())
 }
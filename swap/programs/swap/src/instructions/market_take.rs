@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface}
+};
+
+use crate::{error::ErrorCode, events::OfferTaken, token_a_for_fill, Config, Offer, OrderBook, OrderBookEntry};
+
+use super::{close_vault, transfer_tokens_with_fee, withdraw_from_vault};
+
+/// Remaining accounts per offer: `[offer, maker, vault, maker token account b]`.
+pub const MARKET_TAKE_OFFER_ACCOUNTS: usize = 4;
+
+/// Fills token-for-token offers of one pair, best price first. Offers are passed as
+/// remaining accounts in price order, followed by any transfer hook accounts. Maker token
+/// B accounts must already exist.
+#[derive(Accounts)]
+pub struct MarketTake<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program_a,
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program_b,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: only used as the authority of `fee_token_account_b`, pinned to the config
+    #[account(address = config.fee_recipient)]
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = fee_recipient,
+        associated_token::token_program = token_program_b,
+    )]
+    pub fee_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = order_book.load()?.is_for(&token_mint_a.key(), &token_mint_b.key()) @ ErrorCode::OrderBookMismatch
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    #[account(mut, mint::token_program = token_program_a)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mint::token_program = token_program_b)]
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+
+}
+
+/// Spends up to `max_token_b_amount`, never paying more than `worst_price_b` token B per
+/// `worst_price_a` token A on any offer. Returns the token B actually spent.
+pub fn fill_offers<'info>(ctx: Context<'_, '_, 'info, 'info, MarketTake<'info>>,
+                          offer_count: u8,
+                          max_token_b_amount: u64,
+                          worst_price_a: u64,
+                          worst_price_b: u64) -> Result<u64> {
+
+    require!(max_token_b_amount > 0 && worst_price_a > 0, ErrorCode::InvalidAmount);
+
+    let offer_accounts_len = offer_count as usize * MARKET_TAKE_OFFER_ACCOUNTS;
+    require!(ctx.remaining_accounts.len() >= offer_accounts_len, ErrorCode::MarketAccountsMismatch);
+    let (offer_accounts, hook_accounts) = ctx.remaining_accounts.split_at(offer_accounts_len);
+
+    let accounts = &ctx.accounts;
+    let now = Clock::get()?.unix_timestamp;
+    let worst_price = OrderBookEntry {
+        offer: Pubkey::default(),
+        remaining_a: worst_price_a,
+        remaining_b: worst_price_b,
+    };
+
+    let mut token_b_spent: u64 = 0;
+    let mut previous_price: Option<OrderBookEntry> = None;
+
+    for offer_accounts in offer_accounts.chunks(MARKET_TAKE_OFFER_ACCOUNTS) {
+        let token_b_budget = max_token_b_amount - token_b_spent;
+        if token_b_budget == 0 {
+            break;
+        }
+
+        let mut offer = Account::<Offer>::try_from(&offer_accounts[0])?;
+        let maker = &offer_accounts[1];
+        let vault = InterfaceAccount::<TokenAccount>::try_from(&offer_accounts[2])?;
+        let maker_token_account_b = InterfaceAccount::<TokenAccount>::try_from(&offer_accounts[3])?;
+
+        require_keys_eq!(offer.maker, maker.key(), ErrorCode::MarketAccountsMismatch);
+        require_keys_eq!(offer.token_mint_a, accounts.token_mint_a.key(), ErrorCode::MarketAccountsMismatch);
+        require_keys_eq!(offer.token_mint_b, accounts.token_mint_b.key(), ErrorCode::MarketAccountsMismatch);
+        require_keys_eq!(
+            vault.key(),
+            get_associated_token_address_with_program_id(&offer.key(), &offer.token_mint_a, &accounts.token_program_a.key()),
+            ErrorCode::MarketAccountsMismatch
+        );
+        require_keys_eq!(maker_token_account_b.owner, offer.maker, ErrorCode::MarketAccountsMismatch);
+        require!(!offer.is_expired(now), ErrorCode::OfferExpired);
+        require!(offer.can_be_taken_by(&accounts.taker.key()), ErrorCode::TakerNotAllowed);
+
+        let price = OrderBookEntry {
+            offer: offer.key(),
+            remaining_a: offer.remaining_a,
+            remaining_b: offer.remaining_b,
+        };
+        require!(price.cmp_price(&worst_price) != Ordering::Greater, ErrorCode::WorstPriceExceeded);
+        if let Some(previous_price) = previous_price {
+            require!(previous_price.cmp_price(&price) != Ordering::Greater, ErrorCode::OffersNotSorted);
+        }
+        previous_price = Some(price);
+
+        // Whatever budget is left may be too small to buy any token A from this offer
+        let token_b_amount = token_b_budget.min(offer.remaining_b);
+        if token_a_for_fill(offer.remaining_a, offer.remaining_b, token_b_amount).is_err() {
+            break;
+        }
+        let token_a_amount = offer.fill(token_b_amount)?;
+
+        transfer_tokens_with_fee(
+            &accounts.config,
+            &accounts.taker_token_account_b,
+            &maker_token_account_b,
+            &accounts.fee_token_account_b,
+            &token_b_amount,
+            &accounts.token_mint_b,
+            &accounts.taker,
+            &accounts.token_program_b,
+            hook_accounts,
+        )?;
+
+        // As in `take_offer`, the last fill sweeps any surplus so the vault can be closed
+        let withdraw_amount = if offer.is_filled() { vault.amount } else { token_a_amount };
+        withdraw_from_vault(
+            &offer,
+            &vault,
+            &accounts.taker_token_account_a,
+            &withdraw_amount,
+            &accounts.token_mint_a,
+            &accounts.token_program_a,
+            hook_accounts,
+        )?;
+
+        accounts.order_book.load_mut()?.update(&offer.key(), offer.remaining_a, offer.remaining_b);
+
+        emit!(OfferTaken {
+            id: offer.id,
+            maker: offer.maker,
+            taker: accounts.taker.key(),
+            token_mint_a: offer.token_mint_a,
+            token_mint_b: offer.token_mint_b,
+            token_a_amount,
+            token_b_amount,
+            fee: accounts.config.fee_for(token_b_amount)?,
+            remaining_a: offer.remaining_a,
+            remaining_b: offer.remaining_b,
+        });
+
+        if offer.is_filled() {
+            close_vault(&offer, &vault, &accounts.token_mint_a, &accounts.taker.to_account_info(), &accounts.token_program_a)?;
+            offer.close(maker.clone())?;
+        } else {
+            offer.exit(&crate::ID)?;
+        }
+
+        token_b_spent += token_b_amount;
+    }
+
+    require!(token_b_spent > 0, ErrorCode::NoOffersFilled);
+
+    Ok(token_b_spent)
+}
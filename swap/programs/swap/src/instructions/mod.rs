@@ -1,9 +1,38 @@
+pub mod crank_expired_offer;
+pub use crank_expired_offer::*;
+
+pub mod initialize_config;
+pub use initialize_config::*;
+
+pub mod initialize_order_book;
+pub use initialize_order_book::*;
+
+pub mod make_basket_offer;
+pub use make_basket_offer::*;
+
 pub mod make_offer;
 pub use make_offer::*;
 
+pub mod market_take;
+pub use market_take::*;
+
+pub mod refund_basket_offer;
+pub use refund_basket_offer::*;
+
+pub mod refund_offer;
+pub use refund_offer::*;
 
 pub mod shared;
 pub use shared::*;
 
+pub mod take_basket_offer;
+pub use take_basket_offer::*;
+
 pub mod take_offer;
-pub use take_offer::*;
\ No newline at end of file
+pub use take_offer::*;
+
+pub mod update_config;
+pub use update_config::*;
+
+pub mod update_offer;
+pub use update_offer::*;
\ No newline at end of file
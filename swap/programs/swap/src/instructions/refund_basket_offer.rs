@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::{events::BasketOfferCancelled, BasketOffer};
+
+use super::{withdraw_and_close_basket_vaults, BASKET_OFFERED_LEG_ACCOUNTS};
+
+/// Remaining accounts are `[mint, vault, maker token account, token program]` per offered
+/// leg, then any transfer hook accounts.
+#[derive(Accounts)]
+pub struct RefundBasketOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket_offer", maker.key().as_ref(), basket_offer.id.to_le_bytes().as_ref()],
+        bump = basket_offer.bump
+    )]
+    pub basket_offer: Account<'info, BasketOffer>,
+}
+
+pub fn refund_and_close_vaults<'info>(ctx: Context<'_, '_, 'info, 'info, RefundBasketOffer<'info>>) -> Result<()> {
+
+    let basket_offer = &ctx.accounts.basket_offer;
+    let (leg_accounts, hook_accounts) = ctx.remaining_accounts.split_at(
+        (basket_offer.offered.len() * BASKET_OFFERED_LEG_ACCOUNTS).min(ctx.remaining_accounts.len()),
+    );
+
+    withdraw_and_close_basket_vaults(
+        basket_offer,
+        leg_accounts,
+        &ctx.accounts.maker.to_account_info(),
+        hook_accounts,
+    )?;
+
+    emit!(BasketOfferCancelled {
+        id: basket_offer.id,
+        maker: basket_offer.maker,
+        offered: basket_offer.offered.clone(),
+    });
+
+    Ok(())
+}
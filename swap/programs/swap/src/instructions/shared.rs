@@ -1,28 +1,362 @@
-use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+use std::mem::size_of;
 
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator,
+};
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::{TransferFeeAmount, TransferFeeConfig},
+            BaseStateWithExtensions, StateWithExtensions,
+        },
+        onchain::invoke_transfer_checked,
+    },
+    token_2022_extensions::transfer_fee::{harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint},
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{error::ErrorCode, BasketOffer, Config, Offer, OrderBook, ANCHOR_DISCRIMINATOR};
+
+/// Remaining accounts per offered basket leg: `[mint, vault, token account, token program]`,
+/// where the token account is the maker's source on make and the recipient on take/refund.
+/// Mints that charge a transfer fee must be writable on take/refund so the vault's withheld
+/// fees can be harvested before it is closed.
+pub const BASKET_OFFERED_LEG_ACCOUNTS: usize = 4;
+
+/// Transfers go through `invoke_transfer_checked` so that Token-2022 mints with a
+/// transfer hook get their extra accounts resolved from `additional_accounts`.
 pub fn transfer_tokens<'info> (
     from: &InterfaceAccount<'info, TokenAccount>,
     to: &InterfaceAccount<'info, TokenAccount>,
     amount: &u64,
     mint: &InterfaceAccount<'info, Mint>,
     authority: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    additional_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+
+    require_keys_eq!(*mint.to_account_info().owner, token_program.key(), ErrorCode::TokenProgramMismatch);
+
+    invoke_transfer_checked(
+        token_program.key,
+        from.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        authority.to_account_info(),
+        additional_accounts,
+        *amount,
+        mint.decimals,
+        &[],
+    )?;
+
+    Ok(())
+}
+
+/// Pays `amount` of the wanted token, splitting off the protocol fee. The maker must receive
+/// their share in full, so the payer also covers any Token-2022 transfer fee on it.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens_with_fee<'info>(
+    config: &Config,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    maker_token_account: &InterfaceAccount<'info, TokenAccount>,
+    fee_token_account: &InterfaceAccount<'info, TokenAccount>,
+    amount: &u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    additional_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    let fee = config.fee_for(*amount)?;
+    let maker_amount = amount
+        .checked_sub(fee)
+        .ok_or(ErrorCode::CalculationOverflow)?;
+
+    if fee > 0 {
+        transfer_tokens(from, fee_token_account, &fee, mint, authority, token_program, additional_accounts)?;
+    }
+
+    transfer_tokens(
+        from,
+        maker_token_account,
+        &gross_up_for_transfer_fee(mint, maker_amount)?,
+        mint,
+        authority,
+        token_program,
+        additional_accounts,
+    )
+}
+
+/// Applies `update` to the pair's order book. The book only exists once
+/// `initialize_order_book` has run for the pair; until then offers stay unlisted.
+pub fn update_order_book_if_initialized(order_book: &AccountInfo, update: impl FnOnce(&mut OrderBook)) -> Result<()> {
+    if *order_book.owner != crate::ID {
+        return Ok(());
+    }
+
+    let mut data = order_book.try_borrow_mut_data()?;
+    let end = ANCHOR_DISCRIMINATOR + size_of::<OrderBook>();
+    if data.len() < end || data[..ANCHOR_DISCRIMINATOR] != OrderBook::DISCRIMINATOR {
+        return err!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+    }
+
+    update(bytemuck::from_bytes_mut(&mut data[ANCHOR_DISCRIMINATOR..end]));
+
+    Ok(())
+}
+
+/// Whether `account` holds enough lamports to stay rent exempt at its current size.
+pub fn is_rent_exempt(account: &AccountInfo) -> Result<bool> {
+    Ok(Rent::get()?.is_exempt(account.lamports(), account.data_len()))
+}
+
+pub fn transfer_lamports<'info>(
+    from: &Signer<'info>,
+    to: &AccountInfo<'info>,
+    amount: &u64,
+    system_program: &Program<'info, System>
+) -> Result<()> {
+    let cpi_context = CpiContext::new(
+        system_program.to_account_info(),
+        Transfer {
+            from: from.to_account_info(),
+            to: to.clone(),
+        },
+    );
+
+    transfer(cpi_context, *amount)
+}
+
+/// Native SOL offers escrow lamports in the program-owned `offer` account, so they can
+/// be debited directly without a CPI.
+pub fn withdraw_lamports_from_offer<'info>(
+    offer: &Account<'info, Offer>,
+    to: &AccountInfo<'info>,
+    amount: &u64
+) -> Result<()> {
+    offer.sub_lamports(*amount)?;
+    to.add_lamports(*amount)?;
+
+    Ok(())
+}
+
+pub fn withdraw_from_vault<'info>(
+    offer: &Account<'info, Offer>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: &u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    additional_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    let id_bytes = offer.id.to_le_bytes();
+    let seeds = &[
+        b"offer",
+        offer.maker.as_ref(),
+        &id_bytes[..],
+        &[offer.bump],
+    ];
+    let signer_seeds = [&seeds[..]];
+
+    transfer_from_vault(
+        vault,
+        to,
+        amount,
+        mint,
+        &offer.to_account_info(),
+        &signer_seeds,
+        token_program,
+        additional_accounts,
+    )
+}
+
+pub fn close_vault<'info>(
+    offer: &Account<'info, Offer>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
     token_program: &Interface<'info, TokenInterface>
 ) -> Result<()> {
+    let id_bytes = offer.id.to_le_bytes();
+    let seeds = &[
+        b"offer",
+        offer.maker.as_ref(),
+        &id_bytes[..],
+        &[offer.bump],
+    ];
+    let signer_seeds = [&seeds[..]];
+
+    close_vault_account(
+        vault,
+        mint,
+        destination,
+        &offer.to_account_info(),
+        &signer_seeds,
+        token_program,
+    )
+}
+
+/// Moves tokens out of a vault owned by a program PDA. `authority` signs with `signer_seeds`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_from_vault<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: &u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    token_program: &Interface<'info, TokenInterface>,
+    additional_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    require_keys_eq!(*mint.to_account_info().owner, token_program.key(), ErrorCode::TokenProgramMismatch);
+
+    invoke_transfer_checked(
+        token_program.key,
+        vault.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        authority.clone(),
+        additional_accounts,
+        *amount,
+        mint.decimals,
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
+/// Token-2022 refuses to close a vault that still holds withheld transfer fees, so
+/// those are harvested to the mint first. Harvesting needs no authority.
+pub fn close_vault_account<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    token_program: &Interface<'info, TokenInterface>
+) -> Result<()> {
+    if withheld_transfer_fees(vault)? > 0 {
+        let cpi_context = CpiContext::new(
+            token_program.to_account_info(),
+            HarvestWithheldTokensToMint {
+                token_program_id: token_program.to_account_info(),
+                mint: mint.to_account_info(),
+            },
+        );
+
+        harvest_withheld_tokens_to_mint(cpi_context, vec![vault.to_account_info()])?;
+    }
 
-    let transfer_account_options = TransferChecked{
-        from: from.to_account_info(),
-        mint: mint.to_account_info(),
-        to: to.to_account_info(),
-        authority: authority.to_account_info()
+    let accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: destination.clone(),
+        authority: authority.clone(),
     };
 
-    let cpi_context = 
-    CpiContext::new(
+    let cpi_context = CpiContext::new_with_signer(
         token_program.to_account_info(),
-        transfer_account_options
+        accounts,
+        signer_seeds,
     );
 
-    transfer_checked(cpi_context, *amount, mint.decimals)
+    close_account(cpi_context)
+}
+
+/// Drains every offered vault of a basket offer into the supplied recipient token
+/// accounts and closes the vaults, sending their rent to `rent_destination`.
+pub fn withdraw_and_close_basket_vaults<'info>(
+    basket_offer: &Account<'info, BasketOffer>,
+    leg_accounts: &'info [AccountInfo<'info>],
+    rent_destination: &AccountInfo<'info>,
+    additional_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    require!(
+        leg_accounts.len() == basket_offer.offered.len() * BASKET_OFFERED_LEG_ACCOUNTS,
+        ErrorCode::BasketAccountsMismatch
+    );
+
+    let id_bytes = basket_offer.id.to_le_bytes();
+    let seeds = &[
+        b"basket_offer",
+        basket_offer.maker.as_ref(),
+        &id_bytes[..],
+        &[basket_offer.bump],
+    ];
+    let signer_seeds = [&seeds[..]];
+
+    for (leg, accounts) in basket_offer.offered.iter().zip(leg_accounts.chunks(BASKET_OFFERED_LEG_ACCOUNTS)) {
+        let mint = InterfaceAccount::<Mint>::try_from(&accounts[0])?;
+        let vault = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+        let recipient = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+        let token_program = Interface::<TokenInterface>::try_from(&accounts[3])?;
+
+        require_keys_eq!(mint.key(), leg.mint, ErrorCode::BasketAccountsMismatch);
+        require_keys_eq!(
+            vault.key(),
+            get_associated_token_address_with_program_id(&basket_offer.key(), &leg.mint, &token_program.key()),
+            ErrorCode::BasketAccountsMismatch
+        );
+
+        transfer_from_vault(
+            &vault,
+            &recipient,
+            &vault.amount,
+            &mint,
+            &basket_offer.to_account_info(),
+            &signer_seeds,
+            &token_program,
+            additional_accounts,
+        )?;
+
+        close_vault_account(
+            &vault,
+            &mint,
+            rent_destination,
+            &basket_offer.to_account_info(),
+            &signer_seeds,
+            &token_program,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Transfer fees withheld in a Token-2022 `token_account`, or zero for accounts without them.
+fn withheld_transfer_fees(token_account: &InterfaceAccount<TokenAccount>) -> Result<u64> {
+    let account_info = token_account.to_account_info();
+    if *account_info.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+
+    let account_data = account_info.try_borrow_data()?;
+    let account_state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account_data)?;
+
+    Ok(account_state
+        .get_extension::<TransferFeeAmount>()
+        .map_or(0, |transfer_fee_amount| u64::from(transfer_fee_amount.withheld_amount)))
+}
+
+/// Amount to send so that `net_amount` arrives after any Token-2022 transfer fee on `mint`.
+pub fn gross_up_for_transfer_fee(mint: &InterfaceAccount<Mint>, net_amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(net_amount);
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(net_amount);
+    };
+
+    let fee = transfer_fee_config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, net_amount)
+        .ok_or(ErrorCode::CalculationOverflow)?;
 
+    net_amount
+        .checked_add(fee)
+        .ok_or(ErrorCode::CalculationOverflow.into())
 }
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{error::ErrorCode, events::BasketOfferTaken, BasketOffer, Config};
+
+use super::{transfer_tokens_with_fee, withdraw_and_close_basket_vaults, BASKET_OFFERED_LEG_ACCOUNTS};
+
+/// Remaining accounts per wanted basket leg:
+/// `[mint, taker token account, maker token account, fee token account, token program]`.
+pub const BASKET_WANTED_LEG_ACCOUNTS: usize = 5;
+
+/// Remaining accounts are every wanted leg followed by every offered leg, in the order
+/// stored on the basket offer, then any transfer hook accounts. The maker and fee
+/// recipient token accounts must already exist.
+#[derive(Accounts)]
+pub struct TakeBasketOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket_offer", maker.key().as_ref(), basket_offer.id.to_le_bytes().as_ref()],
+        bump = basket_offer.bump
+    )]
+    pub basket_offer: Account<'info, BasketOffer>,
+}
+
+pub fn send_wanted_basket_tokens_to_maker<'info>(ctx: &Context<'_, '_, 'info, 'info, TakeBasketOffer<'info>>) -> Result<()> {
+
+    let hook_accounts = hook_accounts(ctx)?;
+    let wanted = &ctx.accounts.basket_offer.wanted;
+    let wanted_accounts = &ctx.remaining_accounts[..wanted.len() * BASKET_WANTED_LEG_ACCOUNTS];
+
+    for (leg, accounts) in wanted.iter().zip(wanted_accounts.chunks(BASKET_WANTED_LEG_ACCOUNTS)) {
+        let mint = InterfaceAccount::<Mint>::try_from(&accounts[0])?;
+        let taker_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+        let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+        let fee_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        let token_program = Interface::<TokenInterface>::try_from(&accounts[4])?;
+
+        require_keys_eq!(mint.key(), leg.mint, ErrorCode::BasketAccountsMismatch);
+        require_keys_eq!(maker_token_account.owner, ctx.accounts.maker.key(), ErrorCode::BasketAccountsMismatch);
+        require_keys_eq!(fee_token_account.owner, ctx.accounts.config.fee_recipient, ErrorCode::BasketAccountsMismatch);
+
+        transfer_tokens_with_fee(
+            &ctx.accounts.config,
+            &taker_token_account,
+            &maker_token_account,
+            &fee_token_account,
+            &leg.amount,
+            &mint,
+            &ctx.accounts.taker,
+            &token_program,
+            hook_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn withdraw_and_close_vaults<'info>(ctx: Context<'_, '_, 'info, 'info, TakeBasketOffer<'info>>) -> Result<()> {
+
+    let hook_accounts = hook_accounts(&ctx)?;
+    let basket_offer = &ctx.accounts.basket_offer;
+    let wanted_accounts_len = basket_offer.wanted.len() * BASKET_WANTED_LEG_ACCOUNTS;
+    let offered_accounts_len = basket_offer.offered.len() * BASKET_OFFERED_LEG_ACCOUNTS;
+    let offered_accounts = &ctx.remaining_accounts[wanted_accounts_len..wanted_accounts_len + offered_accounts_len];
+
+    withdraw_and_close_basket_vaults(
+        basket_offer,
+        offered_accounts,
+        &ctx.accounts.taker.to_account_info(),
+        hook_accounts,
+    )?;
+
+    emit!(BasketOfferTaken {
+        id: basket_offer.id,
+        maker: basket_offer.maker,
+        taker: ctx.accounts.taker.key(),
+        offered: basket_offer.offered.clone(),
+        wanted: basket_offer.wanted.clone(),
+    });
+
+    Ok(())
+}
+
+fn hook_accounts<'info>(ctx: &Context<'_, '_, 'info, 'info, TakeBasketOffer<'info>>) -> Result<&'info [AccountInfo<'info>]> {
+    let basket_offer = &ctx.accounts.basket_offer;
+    let legs_accounts_len = basket_offer.wanted.len() * BASKET_WANTED_LEG_ACCOUNTS
+        + basket_offer.offered.len() * BASKET_OFFERED_LEG_ACCOUNTS;
+    require!(ctx.remaining_accounts.len() >= legs_accounts_len, ErrorCode::BasketAccountsMismatch);
+
+    Ok(&ctx.remaining_accounts[legs_accounts_len..])
+}
@@ -2,21 +2,19 @@ use anchor_lang::prelude::*;
 
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{close_account, 
-                      transfer_checked, 
-                      CloseAccount, 
-                      Mint, 
-                      TokenAccount, 
-                      TokenInterface, 
-                      TransferChecked}
+    token_interface::{Mint, TokenAccount, TokenInterface}
 };
 
 
 
-use crate::Offer;
+use crate::{error::ErrorCode, events::OfferTaken, Config, Offer, NATIVE_SOL_SENTINEL};
 
-use super::transfer_tokens;
+use super::{
+    close_vault, transfer_lamports, transfer_tokens_with_fee, update_order_book_if_initialized, withdraw_from_vault,
+    withdraw_lamports_from_offer,
+};
 
+/// Token accounts for a leg are left out when that leg of the offer is native SOL.
 #[derive(Accounts)]
 pub struct TakeOffer<'info> {
     #[account(mut)]
@@ -30,32 +28,53 @@ pub struct TakeOffer<'info> {
         payer = taker,
         associated_token::mint = token_mint_a,
         associated_token::authority = taker,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_a,
     )]
-    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub taker_token_account_a: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(
         mut,
         associated_token::mint = token_mint_b,
         associated_token::authority = taker,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_b,
     )]
-    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub taker_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = token_mint_b,
-        associated_token::authority = taker,
-        associated_token::token_program = token_program,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program_b,
+    )]
+    pub maker_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: receives native SOL fees and owns `fee_token_account_b`, pinned to the config
+    #[account(mut, address = config.fee_recipient)]
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = fee_recipient,
+        associated_token::token_program = token_program_b,
     )]
-    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub fee_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(
         mut,
-        close = maker,
         has_one = maker,
-        has_one = token_mint_a,
+        constraint = offer.token_mint_a == token_mint_a.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        constraint = offer.token_mint_b == token_mint_b.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
         seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
         bump = offer.bump
     )]
@@ -65,68 +84,144 @@ pub struct TakeOffer<'info> {
         mut,
         associated_token::mint = token_mint_a,
         associated_token::authority = offer,
-        associated_token::token_program = token_program,
+        associated_token::token_program = token_program_a,
     )]
-    vault: InterfaceAccount<'info, TokenAccount>,
+    vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the pair's order book, which may not be initialized yet
+    #[account(
+        mut,
+        seeds = [b"order_book", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump
+    )]
+    pub order_book: UncheckedAccount<'info>,
+
+    #[account(mut, mint::token_program = token_program_a)]
+    pub token_mint_a: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mint::token_program = token_program_b)]
+    pub token_mint_b: Option<InterfaceAccount<'info, Mint>>,
 
-    pub token_mint_a: InterfaceAccount<'info, Mint>,
-    pub token_mint_b: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_a: Option<Interface<'info, TokenInterface>>,
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
     pub associated_token_program: Program<'info, AssociatedToken>
 
 }
 
-pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>) -> Result<()>{
+pub fn send_wanted_tokens_to_maker<'info>(ctx: &Context<'_, '_, '_, 'info, TakeOffer<'info>>, token_b_amount: u64) -> Result<()>{
+
+    let accounts = &ctx.accounts;
+    if accounts.offer.is_native_b() {
+        let fee = accounts.config.fee_for(token_b_amount)?;
+        let maker_amount = token_b_amount
+            .checked_sub(fee)
+            .ok_or(ErrorCode::CalculationOverflow)?;
+
+        if fee > 0 {
+            transfer_lamports(&accounts.taker, &accounts.fee_recipient, &fee, &accounts.system_program)?;
+        }
+        return transfer_lamports(&accounts.taker, &accounts.maker, &maker_amount, &accounts.system_program);
+    }
+
+    let (
+        Some(taker_token_account_b),
+        Some(maker_token_account_b),
+        Some(fee_token_account_b),
+        Some(token_mint_b),
+        Some(token_program_b),
+    ) = (
+        &accounts.taker_token_account_b,
+        &accounts.maker_token_account_b,
+        &accounts.fee_token_account_b,
+        &accounts.token_mint_b,
+        &accounts.token_program_b,
+    ) else {
+        return err!(ErrorCode::IncompleteTokenAccounts);
+    };
 
-    transfer_tokens(
-        &ctx.accounts.taker_token_account_b, 
-        &ctx.accounts.maker_token_account_b,
-        &ctx.accounts.offer.token_b_wanted_amount,
-        &ctx.accounts.token_mint_b,
-        &ctx.accounts.taker, 
-        &ctx.accounts.token_program)
+    transfer_tokens_with_fee(
+        &accounts.config,
+        taker_token_account_b,
+        maker_token_account_b,
+        fee_token_account_b,
+        &token_b_amount,
+        token_mint_b,
+        &accounts.taker,
+        token_program_b,
+        ctx.remaining_accounts,
+    )
 }
 
-pub fn withdraw_and_close_vault(context: Context<TakeOffer>) -> Result<()> {
-    let seeds = &[
-        b"offer",
-        context.accounts.maker.to_account_info().key.as_ref(),
-        &context.accounts.offer.id.to_le_bytes()[..],
-        &[context.accounts.offer.bump],
-    ];
-    let signer_seeds = [&seeds[..]];
-
-    let accounts = TransferChecked {
-        from: context.accounts.vault.to_account_info(),
-        to: context.accounts.taker_token_account_a.to_account_info(),
-        mint: context.accounts.token_mint_a.to_account_info(),
-        authority: context.accounts.offer.to_account_info(),
-    };
+pub fn emit_offer_taken(ctx: &Context<TakeOffer>, token_a_amount: u64, token_b_amount: u64) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: ctx.accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount,
+        token_b_amount,
+        fee: ctx.accounts.config.fee_for(token_b_amount)?,
+        remaining_a: offer.remaining_a,
+        remaining_b: offer.remaining_b,
+    });
+
+    Ok(())
+}
 
-    let cpi_context = CpiContext::new_with_signer(
-        context.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
-    );
-
-    transfer_checked(
-        cpi_context,
-        context.accounts.vault.amount,
-        context.accounts.token_mint_a.decimals,
-    )?;
-
-    let accounts = CloseAccount {
-        account: context.accounts.vault.to_account_info(),
-        destination: context.accounts.taker.to_account_info(),
-        authority: context.accounts.offer.to_account_info(),
-    };
+pub fn update_order_book(ctx: &Context<TakeOffer>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
 
-    let cpi_context = CpiContext::new_with_signer(
-        context.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
-    );
+    update_order_book_if_initialized(&ctx.accounts.order_book, |order_book| {
+        order_book.update(&offer.key(), offer.remaining_a, offer.remaining_b);
+    })
+}
 
-    close_account(cpi_context)
+pub fn withdraw_and_close_vault<'info>(context: Context<'_, '_, '_, 'info, TakeOffer<'info>>, token_a_amount: u64) -> Result<()> {
+    let accounts = &context.accounts;
+    if accounts.offer.is_native_a() {
+        withdraw_lamports_from_offer(&accounts.offer, &accounts.taker, &token_a_amount)?;
+    } else {
+        let (Some(taker_token_account_a), Some(vault), Some(token_mint_a), Some(token_program_a)) = (
+            &accounts.taker_token_account_a,
+            &accounts.vault,
+            &accounts.token_mint_a,
+            &accounts.token_program_a,
+        ) else {
+            return err!(ErrorCode::IncompleteTokenAccounts);
+        };
+
+        // The last fill also sweeps anything sent to the vault on top of the offer, since a
+        // vault with a balance left in it cannot be closed
+        let withdraw_amount = if accounts.offer.is_filled() { vault.amount } else { token_a_amount };
+        withdraw_from_vault(
+            &accounts.offer,
+            vault,
+            taker_token_account_a,
+            &withdraw_amount,
+            token_mint_a,
+            token_program_a,
+            context.remaining_accounts,
+        )?;
+
+        // Partially filled offers keep their vault until the last fill drains it
+        if accounts.offer.is_filled() {
+            close_vault(
+                &accounts.offer,
+                vault,
+                token_mint_a,
+                &accounts.taker.to_account_info(),
+                token_program_a,
+            )?;
+        }
+    }
+
+    if !accounts.offer.is_filled() {
+        return Ok(());
+    }
+
+    accounts.offer.close(accounts.maker.to_account_info())
 }
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, MAX_FEE_BASIS_POINTS};
+
+use super::is_rent_exempt;
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: only its balance is read, see `Config::fee_recipient`
+    #[account(constraint = is_rent_exempt(&fee_recipient)? @ ErrorCode::FeeRecipientNotRentExempt)]
+    pub fee_recipient: UncheckedAccount<'info>,
+}
+
+pub fn change_config(ctx: Context<UpdateConfig>, fee_basis_points: u16) -> Result<()> {
+
+    require!(fee_basis_points <= MAX_FEE_BASIS_POINTS, ErrorCode::FeeTooHigh);
+
+    let config = &mut ctx.accounts.config;
+    config.fee_basis_points = fee_basis_points;
+    config.fee_recipient = ctx.accounts.fee_recipient.key();
+
+    Ok(())
+}
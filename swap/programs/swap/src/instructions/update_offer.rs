@@ -0,0 +1,153 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface}
+};
+
+use crate::{error::ErrorCode, events::OfferUpdated, Offer, NATIVE_SOL_SENTINEL};
+
+use super::{
+    transfer_lamports, transfer_tokens, update_order_book_if_initialized, withdraw_from_vault, withdraw_lamports_from_offer,
+};
+
+/// Amends an offer in place, so the offer PDA and its vault keep their addresses.
+/// Token accounts are left out when token A is native SOL.
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one = maker,
+        constraint = offer.token_mint_a == token_mint_a.as_ref().map_or(NATIVE_SOL_SENTINEL, |mint| mint.key()),
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the pair's order book, which may not be initialized yet
+    #[account(
+        mut,
+        seeds = [b"order_book", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump
+    )]
+    pub order_book: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+
+}
+
+/// Moves `token_a_top_up_amount` into the offer and returns what actually arrived,
+/// net of any Token-2022 transfer fee.
+pub fn top_up_vault<'info>(ctx: &mut Context<'_, '_, '_, 'info, UpdateOffer<'info>>, token_a_top_up_amount: u64) -> Result<u64> {
+    if token_a_top_up_amount == 0 {
+        return Ok(0);
+    }
+
+    let accounts = &mut ctx.accounts;
+    if accounts.offer.is_native_a() {
+        transfer_lamports(
+            &accounts.maker,
+            &accounts.offer.to_account_info(),
+            &token_a_top_up_amount,
+            &accounts.system_program,
+        )?;
+        return Ok(token_a_top_up_amount);
+    }
+
+    let (Some(maker_token_account_a), Some(vault), Some(token_mint_a), Some(token_program)) = (
+        &accounts.maker_token_account_a,
+        &mut accounts.vault,
+        &accounts.token_mint_a,
+        &accounts.token_program,
+    ) else {
+        return err!(ErrorCode::IncompleteTokenAccounts);
+    };
+
+    let balance_before = vault.amount;
+    transfer_tokens(
+        maker_token_account_a,
+        vault,
+        &token_a_top_up_amount,
+        token_mint_a,
+        &accounts.maker,
+        token_program,
+        ctx.remaining_accounts,
+    )?;
+    vault.reload()?;
+
+    Ok(vault.amount - balance_before)
+}
+
+pub fn withdraw_to_maker<'info>(ctx: &Context<'_, '_, '_, 'info, UpdateOffer<'info>>, token_a_withdraw_amount: u64) -> Result<()> {
+    if token_a_withdraw_amount == 0 {
+        return Ok(());
+    }
+
+    let accounts = &ctx.accounts;
+    if accounts.offer.is_native_a() {
+        return withdraw_lamports_from_offer(&accounts.offer, &accounts.maker, &token_a_withdraw_amount);
+    }
+
+    let (Some(maker_token_account_a), Some(vault), Some(token_mint_a), Some(token_program)) = (
+        &accounts.maker_token_account_a,
+        &accounts.vault,
+        &accounts.token_mint_a,
+        &accounts.token_program,
+    ) else {
+        return err!(ErrorCode::IncompleteTokenAccounts);
+    };
+
+    withdraw_from_vault(
+        &accounts.offer,
+        vault,
+        maker_token_account_a,
+        &token_a_withdraw_amount,
+        token_mint_a,
+        token_program,
+        ctx.remaining_accounts,
+    )
+}
+
+pub fn save_amendment(ctx: Context<UpdateOffer>, token_a_deposited_amount: u64, token_a_withdrawn_amount: u64) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+
+    update_order_book_if_initialized(&ctx.accounts.order_book, |order_book| {
+        order_book.update(&offer.key(), offer.remaining_a, offer.remaining_b);
+    })?;
+
+    emit!(OfferUpdated {
+        id: offer.id,
+        maker: offer.maker,
+        token_a_deposited_amount,
+        token_a_withdrawn_amount,
+        remaining_a: offer.remaining_a,
+        remaining_b: offer.remaining_b,
+    });
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 pub mod constants;
+pub mod cpi_helpers;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
@@ -16,19 +18,81 @@ pub mod swap {
 
     use super::*;
 
-    pub fn make_offer(ctx: Context<MakeOffer>, 
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_basis_points: u16) -> Result<()> {
+        instructions::initialize_config::save_config(ctx, fee_basis_points)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, fee_basis_points: u16) -> Result<()> {
+        instructions::update_config::change_config(ctx, fee_basis_points)
+    }
+
+    pub fn initialize_order_book(ctx: Context<InitializeOrderBook>, token_mint_a: Pubkey, token_mint_b: Pubkey) -> Result<()> {
+        instructions::initialize_order_book::save_order_book(ctx, token_mint_a, token_mint_b)
+    }
+
+    pub fn make_offer<'info>(ctx: Context<'_, '_, '_, 'info, MakeOffer<'info>>, 
                       id: u64,
                       token_a_offered_amount: u64, 
-                      token_b_wanted_amount: u64) -> Result<()> {
+                      token_b_wanted_amount: u64,
+                      expires_at: i64,
+                      allowed_taker: Option<Pubkey>) -> Result<()> {
 
         instructions::make_offer::send_offered_tokens_to_vault(&ctx, token_a_offered_amount)?;
-        instructions::make_offer::save_offer(ctx, id, token_b_wanted_amount)
+        instructions::make_offer::save_offer(ctx, id, token_a_offered_amount, token_b_wanted_amount, expires_at, allowed_taker)
+
+    }
+
+    pub fn take_offer<'info>(context: Context<'_, '_, '_, 'info, TakeOffer<'info>>, token_b_amount: u64) -> Result<()> {
+        let token_a_amount = context.accounts.offer.fill(token_b_amount)?;
+        instructions::take_offer::send_wanted_tokens_to_maker(&context, token_b_amount)?;
+        instructions::take_offer::emit_offer_taken(&context, token_a_amount, token_b_amount)?;
+        instructions::take_offer::update_order_book(&context)?;
+        instructions::take_offer::withdraw_and_close_vault(context, token_a_amount)
+    }
+
+    pub fn market_take<'info>(ctx: Context<'_, '_, 'info, 'info, MarketTake<'info>>,
+                              offer_count: u8,
+                              max_token_b_amount: u64,
+                              worst_price_a: u64,
+                              worst_price_b: u64) -> Result<u64> {
+        instructions::market_take::fill_offers(ctx, offer_count, max_token_b_amount, worst_price_a, worst_price_b)
+    }
+
+    pub fn update_offer<'info>(mut ctx: Context<'_, '_, '_, 'info, UpdateOffer<'info>>,
+                               token_b_wanted_amount: u64,
+                               token_a_top_up_amount: u64,
+                               token_a_withdraw_amount: u64) -> Result<()> {
+        let token_a_deposited_amount = instructions::update_offer::top_up_vault(&mut ctx, token_a_top_up_amount)?;
+        ctx.accounts.offer.amend(token_a_deposited_amount, token_a_withdraw_amount, token_b_wanted_amount)?;
+        instructions::update_offer::withdraw_to_maker(&ctx, token_a_withdraw_amount)?;
+        instructions::update_offer::save_amendment(ctx, token_a_deposited_amount, token_a_withdraw_amount)
+    }
+
+    pub fn refund_offer<'info>(context: Context<'_, '_, '_, 'info, RefundOffer<'info>>) -> Result<()> {
+        instructions::refund_offer::refund_and_close_vault(context)
+    }
+
+    pub fn crank_expired_offer<'info>(context: Context<'_, '_, '_, 'info, CrankExpiredOffer<'info>>) -> Result<()> {
+        instructions::crank_expired_offer::refund_expired_and_close_vault(context)
+    }
+
+    pub fn make_basket_offer<'info>(ctx: Context<'_, '_, 'info, 'info, MakeBasketOffer<'info>>, 
+                                    id: u64,
+                                    token_a_offered_amounts: Vec<u64>,
+                                    wanted: Vec<BasketLeg>) -> Result<()> {
+
+        let offered = instructions::make_basket_offer::send_offered_tokens_to_vaults(&ctx, &token_a_offered_amounts)?;
+        instructions::make_basket_offer::save_basket_offer(ctx, id, offered, wanted)
+
+    }
 
+    pub fn take_basket_offer<'info>(ctx: Context<'_, '_, 'info, 'info, TakeBasketOffer<'info>>) -> Result<()> {
+        instructions::take_basket_offer::send_wanted_basket_tokens_to_maker(&ctx)?;
+        instructions::take_basket_offer::withdraw_and_close_vaults(ctx)
     }
 
-    pub fn take_offer(context: Context<TakeOffer>) -> Result<()> {
-        instructions::take_offer::send_wanted_tokens_to_maker(&context)?;
-        instructions::take_offer::withdraw_and_close_vault(context)
+    pub fn refund_basket_offer<'info>(ctx: Context<'_, '_, 'info, 'info, RefundBasketOffer<'info>>) -> Result<()> {
+        instructions::refund_basket_offer::refund_and_close_vaults(ctx)
     }
 }
 
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::MAX_BASKET_LEGS;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct BasketLeg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// An all-or-nothing trade of several offered mints for several wanted mints.
+/// Each offered leg is escrowed in the basket offer's associated token account for that mint.
+#[account]
+#[derive(InitSpace)]
+pub struct BasketOffer {
+    pub id: u64,
+    pub maker: Pubkey,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub offered: Vec<BasketLeg>,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub wanted: Vec<BasketLeg>,
+    pub bump: u8,
+}
+
+impl BasketOffer {
+    pub fn validate_legs(legs: &[BasketLeg]) -> bool {
+        !legs.is_empty()
+            && legs.len() <= MAX_BASKET_LEGS
+            && legs.iter().all(|leg| leg.amount > 0)
+            && legs
+                .iter()
+                .enumerate()
+                .all(|(i, leg)| legs[..i].iter().all(|other| other.mint != leg.mint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(mint: Pubkey, amount: u64) -> BasketLeg {
+        BasketLeg { mint, amount }
+    }
+
+    #[test]
+    fn accepts_unique_legs_up_to_the_limit() {
+        let legs: Vec<_> = (0..MAX_BASKET_LEGS).map(|_| leg(Pubkey::new_unique(), 1)).collect();
+        assert!(BasketOffer::validate_legs(&legs));
+    }
+
+    #[test]
+    fn rejects_empty_oversized_zero_and_duplicate_legs() {
+        let mint = Pubkey::new_unique();
+        let too_many: Vec<_> = (0..=MAX_BASKET_LEGS).map(|_| leg(Pubkey::new_unique(), 1)).collect();
+
+        assert!(!BasketOffer::validate_legs(&[]));
+        assert!(!BasketOffer::validate_legs(&too_many));
+        assert!(!BasketOffer::validate_legs(&[leg(mint, 0)]));
+        assert!(!BasketOffer::validate_legs(&[leg(mint, 1), leg(mint, 2)]));
+    }
+}
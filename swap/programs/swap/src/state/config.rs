@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, BASIS_POINTS_DIVISOR};
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub fee_basis_points: u16,
+    /// Must stay rent exempt, since native SOL fees reach it as plain lamport transfers
+    /// and the runtime rejects those into an account left below the minimum.
+    pub fee_recipient: Pubkey,
+    pub bump: u8,
+}
+
+impl Config {
+    /// Protocol fee owed on `amount`, rounded down in favour of the maker.
+    pub fn fee_for(&self, amount: u64) -> Result<u64> {
+        let fee = (amount as u128)
+            .checked_mul(self.fee_basis_points as u128)
+            .ok_or(ErrorCode::CalculationOverflow)?
+            / BASIS_POINTS_DIVISOR as u128;
+
+        u64::try_from(fee).map_err(|_| ErrorCode::CalculationOverflow.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_fee(fee_basis_points: u16) -> Config {
+        Config {
+            admin: Pubkey::default(),
+            fee_basis_points,
+            fee_recipient: Pubkey::default(),
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn fee_is_rounded_down() {
+        // 30 bps of 999 = 2.997
+        assert_eq!(config_with_fee(30).fee_for(999).unwrap(), 2);
+        assert_eq!(config_with_fee(30).fee_for(10_000).unwrap(), 30);
+    }
+
+    #[test]
+    fn zero_fee_takes_nothing() {
+        assert_eq!(config_with_fee(0).fee_for(u64::MAX).unwrap(), 0);
+    }
+}
@@ -1,2 +1,11 @@
+pub mod basket_offer;
+pub use basket_offer::*;
+
+pub mod config;
+pub use config::*;
+
 pub mod offer;
-pub use offer::*;
\ No newline at end of file
+pub use offer::*;
+
+pub mod order_book;
+pub use order_book::*;
\ No newline at end of file
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::{error::ErrorCode, NATIVE_SOL_SENTINEL};
+
 #[account]
 #[derive(InitSpace)]
 pub struct Offer{
@@ -7,6 +9,194 @@ pub struct Offer{
     pub maker: Pubkey,
     pub token_mint_a: Pubkey,
     pub token_mint_b: Pubkey,
+    /// What the vault actually received, net of any Token-2022 transfer fee
+    pub token_a_offered_amount: u64,
     pub token_b_wanted_amount: u64,
+    pub remaining_a: u64,
+    pub remaining_b: u64,
+    pub expires_at: i64,
+    pub allowed_taker: Option<Pubkey>,
     pub bump: u8,
-}
\ No newline at end of file
+}
+
+impl Offer {
+    /// Records a fill of `token_b_amount` and returns how much token A it releases.
+    pub fn fill(&mut self, token_b_amount: u64) -> Result<u64> {
+        let token_a_amount = token_a_for_fill(self.remaining_a, self.remaining_b, token_b_amount)?;
+
+        self.remaining_a -= token_a_amount;
+        self.remaining_b -= token_b_amount;
+
+        Ok(token_a_amount)
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.remaining_b == 0
+    }
+
+    pub fn is_native_a(&self) -> bool {
+        self.token_mint_a == NATIVE_SOL_SENTINEL
+    }
+
+    pub fn is_native_b(&self) -> bool {
+        self.token_mint_b == NATIVE_SOL_SENTINEL
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Re-prices what is left of the offer at `token_b_wanted_amount` after adding
+    /// `token_a_deposited` and taking out `token_a_withdrawn`. Filled amounts stay in the totals.
+    pub fn amend(&mut self, token_a_deposited: u64, token_a_withdrawn: u64, token_b_wanted_amount: u64) -> Result<()> {
+        require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+
+        let remaining_a = self
+            .remaining_a
+            .checked_add(token_a_deposited)
+            .ok_or(ErrorCode::CalculationOverflow)?
+            .checked_sub(token_a_withdrawn)
+            .ok_or(ErrorCode::WithdrawalTooLarge)?;
+        require!(remaining_a > 0, ErrorCode::WithdrawalTooLarge);
+
+        let token_b_filled = self.token_b_wanted_amount - self.remaining_b;
+        self.token_a_offered_amount = self
+            .token_a_offered_amount
+            .checked_add(token_a_deposited)
+            .ok_or(ErrorCode::CalculationOverflow)?
+            - token_a_withdrawn;
+        self.token_b_wanted_amount = token_b_filled
+            .checked_add(token_b_wanted_amount)
+            .ok_or(ErrorCode::CalculationOverflow)?;
+        self.remaining_a = remaining_a;
+        self.remaining_b = token_b_wanted_amount;
+
+        Ok(())
+    }
+
+    /// Private offers can only be taken by the taker the maker pinned at creation.
+    pub fn can_be_taken_by(&self, taker: &Pubkey) -> bool {
+        self.allowed_taker.is_none() || self.allowed_taker.as_ref() == Some(taker)
+    }
+}
+
+/// Pro-rata token A owed for `token_b_amount` at the remaining ratio, rounded down
+/// so any dust stays with the maker. Filling all of `remaining_b` releases all of `remaining_a`.
+pub fn token_a_for_fill(remaining_a: u64, remaining_b: u64, token_b_amount: u64) -> Result<u64> {
+    require!(
+        token_b_amount > 0 && token_b_amount <= remaining_b,
+        ErrorCode::InvalidFillAmount
+    );
+
+    let token_a_amount = (remaining_a as u128)
+        .checked_mul(token_b_amount as u128)
+        .ok_or(ErrorCode::CalculationOverflow)?
+        / remaining_b as u128;
+
+    require!(token_a_amount > 0, ErrorCode::FillTooSmall);
+
+    u64::try_from(token_a_amount).map_err(|_| ErrorCode::CalculationOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer_for(token_a_offered_amount: u64, token_b_wanted_amount: u64) -> Offer {
+        Offer {
+            id: 0,
+            maker: Pubkey::default(),
+            token_mint_a: Pubkey::default(),
+            token_mint_b: Pubkey::default(),
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            remaining_a: token_a_offered_amount,
+            remaining_b: token_b_wanted_amount,
+            expires_at: 0,
+            allowed_taker: None,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn full_fill_releases_everything() {
+        assert_eq!(token_a_for_fill(1_000, 333, 333).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn partial_fill_is_pro_rata() {
+        assert_eq!(token_a_for_fill(1_000, 500, 250).unwrap(), 500);
+    }
+
+    #[test]
+    fn partial_fill_rounds_down_for_maker() {
+        // 100 * 1 / 3 = 33.33.. -> the taker gets 33
+        assert_eq!(token_a_for_fill(100, 3, 1).unwrap(), 33);
+    }
+
+    #[test]
+    fn large_amounts_do_not_overflow() {
+        assert_eq!(token_a_for_fill(u64::MAX, u64::MAX, u64::MAX / 2).unwrap(), u64::MAX / 2);
+    }
+
+    #[test]
+    fn rejects_zero_and_overfill() {
+        assert_eq!(token_a_for_fill(100, 10, 0).unwrap_err(), ErrorCode::InvalidFillAmount.into());
+        assert_eq!(token_a_for_fill(100, 10, 11).unwrap_err(), ErrorCode::InvalidFillAmount.into());
+    }
+
+    #[test]
+    fn rejects_fill_that_releases_nothing() {
+        assert_eq!(token_a_for_fill(1, 10, 5).unwrap_err(), ErrorCode::FillTooSmall.into());
+    }
+
+    #[test]
+    fn private_offer_only_allows_pinned_taker() {
+        let allowed = Pubkey::new_unique();
+        let mut offer = offer_for(1, 1);
+        assert!(offer.can_be_taken_by(&Pubkey::new_unique()));
+
+        offer.allowed_taker = Some(allowed);
+        assert!(offer.can_be_taken_by(&allowed));
+        assert!(!offer.can_be_taken_by(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn successive_fills_drain_offer_exactly() {
+        let mut offer = offer_for(100, 7);
+
+        let mut released = 0;
+        for token_b_amount in [2, 2, 3] {
+            released += offer.fill(token_b_amount).unwrap();
+        }
+
+        assert!(offer.is_filled());
+        assert_eq!(offer.remaining_a, 0);
+        assert_eq!(released, 100);
+    }
+
+    #[test]
+    fn amend_reprices_remaining_and_keeps_fills() {
+        let mut offer = offer_for(100, 10);
+        offer.fill(4).unwrap();
+
+        offer.amend(20, 0, 12).unwrap();
+        assert_eq!(offer.remaining_a, 80);
+        assert_eq!(offer.remaining_b, 12);
+        assert_eq!(offer.token_a_offered_amount, 120);
+        assert_eq!(offer.token_b_wanted_amount, 16);
+
+        offer.amend(0, 30, 6).unwrap();
+        assert_eq!(offer.remaining_a, 50);
+        assert_eq!(offer.token_a_offered_amount, 90);
+        assert_eq!(offer.token_b_wanted_amount, 10);
+    }
+
+    #[test]
+    fn amend_cannot_withdraw_everything_or_want_nothing() {
+        let mut offer = offer_for(100, 10);
+        assert_eq!(offer.amend(0, 100, 10).unwrap_err(), ErrorCode::WithdrawalTooLarge.into());
+        assert_eq!(offer.amend(0, 101, 10).unwrap_err(), ErrorCode::WithdrawalTooLarge.into());
+        assert_eq!(offer.amend(0, 0, 0).unwrap_err(), ErrorCode::InvalidAmount.into());
+    }
+}
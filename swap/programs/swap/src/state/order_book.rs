@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+
+use crate::MAX_ORDER_BOOK_ENTRIES;
+
+#[zero_copy]
+pub struct OrderBookEntry {
+    pub offer: Pubkey,
+    pub remaining_a: u64,
+    pub remaining_b: u64,
+}
+
+impl OrderBookEntry {
+    /// Compares token B asked per token A, so the cheapest offer for a taker sorts first.
+    pub fn cmp_price(&self, other: &Self) -> Ordering {
+        (self.remaining_b as u128 * other.remaining_a as u128)
+            .cmp(&(other.remaining_b as u128 * self.remaining_a as u128))
+    }
+}
+
+/// Open offers for one token A / token B pair, sorted from best to worst price for a taker.
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub len: u64,
+    pub entries: [OrderBookEntry; MAX_ORDER_BOOK_ENTRIES],
+    pub bump: u8,
+    pub padding: [u8; 7],
+}
+
+impl OrderBook {
+    pub fn is_for(&self, token_mint_a: &Pubkey, token_mint_b: &Pubkey) -> bool {
+        self.token_mint_a == *token_mint_a && self.token_mint_b == *token_mint_b
+    }
+
+    pub fn entries(&self) -> &[OrderBookEntry] {
+        &self.entries[..self.len as usize]
+    }
+
+    /// Inserts behind any entries at the same price, keeping time priority. A full book
+    /// evicts its worst entry for a better priced one, otherwise the offer stays unlisted.
+    /// Returns whether the entry was listed.
+    pub fn insert(&mut self, entry: OrderBookEntry) -> bool {
+        let len = self.len as usize;
+        let index = self.entries()
+            .iter()
+            .position(|existing| existing.cmp_price(&entry) == Ordering::Greater)
+            .unwrap_or(len);
+
+        if len == MAX_ORDER_BOOK_ENTRIES {
+            if index == len {
+                return false;
+            }
+            self.entries.copy_within(index..len - 1, index + 1);
+        } else {
+            self.entries.copy_within(index..len, index + 1);
+            self.len += 1;
+        }
+        self.entries[index] = entry;
+
+        true
+    }
+
+    /// Removes the entry for `offer` if it is listed. Offers evicted from a full book are not.
+    pub fn remove(&mut self, offer: &Pubkey) {
+        let len = self.len as usize;
+        if let Some(index) = self.entries().iter().position(|entry| entry.offer == *offer) {
+            self.entries.copy_within(index + 1..len, index);
+            self.len -= 1;
+        }
+    }
+
+    /// Re-prices a partially filled offer, or drops it once it is filled.
+    pub fn update(&mut self, offer: &Pubkey, remaining_a: u64, remaining_b: u64) {
+        let listed = self.entries().iter().any(|entry| entry.offer == *offer);
+        self.remove(offer);
+
+        if listed && remaining_b > 0 {
+            self.insert(OrderBookEntry {
+                offer: *offer,
+                remaining_a,
+                remaining_b,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::__private::bytemuck::Zeroable;
+
+    use super::*;
+
+    fn entry(remaining_a: u64, remaining_b: u64) -> OrderBookEntry {
+        OrderBookEntry {
+            offer: Pubkey::new_unique(),
+            remaining_a,
+            remaining_b,
+        }
+    }
+
+    fn offers(book: &OrderBook) -> Vec<Pubkey> {
+        book.entries().iter().map(|entry| entry.offer).collect()
+    }
+
+    #[test]
+    fn keeps_entries_sorted_by_price_then_time() {
+        let mut book = OrderBook::zeroed();
+        let expensive = entry(1, 3);
+        let cheap = entry(2, 2);
+        let same_price_later = entry(4, 4);
+
+        assert!(book.insert(expensive));
+        assert!(book.insert(cheap));
+        assert!(book.insert(same_price_later));
+
+        assert_eq!(offers(&book), vec![cheap.offer, same_price_later.offer, expensive.offer]);
+    }
+
+    #[test]
+    fn full_book_evicts_worst_or_leaves_offer_unlisted() {
+        let mut book = OrderBook::zeroed();
+        for _ in 0..MAX_ORDER_BOOK_ENTRIES {
+            assert!(book.insert(entry(1, 10)));
+        }
+
+        let worse = entry(1, 11);
+        assert!(!book.insert(worse));
+        assert!(book.entries().iter().all(|listed| listed.offer != worse.offer));
+
+        let better = entry(1, 1);
+        assert!(book.insert(better));
+        assert_eq!(book.entries().len(), MAX_ORDER_BOOK_ENTRIES);
+        assert_eq!(book.entries()[0].offer, better.offer);
+    }
+
+    #[test]
+    fn update_reprices_and_removes_filled_offers() {
+        let mut book = OrderBook::zeroed();
+        let first = entry(1, 1);
+        let second = entry(1, 2);
+        assert!(book.insert(first));
+        assert!(book.insert(second));
+
+        book.update(&first.offer, 1, 5);
+        assert_eq!(offers(&book), vec![second.offer, first.offer]);
+
+        book.update(&first.offer, 0, 0);
+        assert_eq!(offers(&book), vec![second.offer]);
+
+        book.remove(&second.offer);
+        assert!(book.entries().is_empty());
+    }
+}
@@ -0,0 +1,807 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{bpf_loader_upgradeable::{self, UpgradeableLoaderState}, entrypoint::ProgramResult, instruction::Instruction, program_pack::Pack, system_instruction, system_program},
+    InstructionData,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address, get_associated_token_address_with_program_id, spl_associated_token_account},
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_fee, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    },
+};
+use solana_program_test::{find_file, processor, read_file, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use swap::{
+    cpi_helpers::{find_config_address, find_offer_address, find_order_book_address, get_vault_address},
+    NATIVE_SOL_SENTINEL,
+};
+
+const FEE_BASIS_POINTS: u16 = 100;
+const STARTING_BALANCE: u64 = 1_000_000;
+const TRANSFER_FEE_BASIS_POINTS: u16 = 100;
+
+// Anchor's entrypoint ties the accounts slice and the account data to one lifetime,
+// which the builtin processor signature cannot name.
+fn process_instruction<'a, 'b, 'c, 'd>(program_id: &'a Pubkey, accounts: &'b [AccountInfo<'c>], data: &'d [u8]) -> ProgramResult {
+    // SAFETY: the slice outlives this call and the program keeps no references past it.
+    let accounts: &'c [AccountInfo<'c>] = unsafe { std::mem::transmute(accounts) };
+    swap::entry(program_id, accounts, data)
+}
+
+/// Set `token_mint_a` or `token_mint_b` to `NATIVE_SOL_SENTINEL` to trade native SOL on that leg.
+struct Env {
+    context: ProgramTestContext,
+    admin: Keypair,
+    maker: Keypair,
+    taker: Keypair,
+    fee_recipient: Pubkey,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+    token_program_a: Pubkey,
+}
+
+impl Env {
+    async fn new() -> Self {
+        Self::with_token_program_a(spl_token::ID).await
+    }
+
+    /// Token A is a Token-2022 mint charging a transfer fee when `token_program_a` is
+    /// Token-2022, and a plain SPL mint otherwise.
+    async fn with_token_program_a(token_program_a: Pubkey) -> Self {
+        let mut program_test = ProgramTest::new("swap", swap::ID, processor!(process_instruction));
+        let admin = Keypair::new();
+        let fee_recipient = Keypair::new().pubkey();
+        add_config(&mut program_test, admin.pubkey(), fee_recipient);
+        let mut context = program_test.start_with_context().await;
+
+        let maker = Keypair::new();
+        let taker = Keypair::new();
+        let payer = context.payer.pubkey();
+
+        process(
+            &mut context,
+            &[
+                system_instruction::transfer(&payer, &maker.pubkey(), 1_000_000_000),
+                system_instruction::transfer(&payer, &taker.pubkey(), 1_000_000_000),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let token_mint_a = if token_program_a == spl_token_2022::ID {
+            create_transfer_fee_mint(&mut context).await
+        } else {
+            create_mint(&mut context).await
+        };
+        let token_mint_b = create_mint(&mut context).await;
+        mint_to_owner(&mut context, &token_mint_a, &maker.pubkey(), STARTING_BALANCE, &token_program_a).await;
+        mint_to_owner(&mut context, &token_mint_b, &taker.pubkey(), STARTING_BALANCE, &spl_token::ID).await;
+
+        let initialize_order_book = Instruction {
+            program_id: swap::ID,
+            accounts: swap::accounts::InitializeOrderBook {
+                payer,
+                order_book: find_order_book_address(&token_mint_a, &token_mint_b).0,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: swap::instruction::InitializeOrderBook { token_mint_a, token_mint_b }.data(),
+        };
+        process(&mut context, &[initialize_order_book], &[])
+            .await
+            .unwrap();
+
+        Self {
+            context,
+            admin,
+            maker,
+            taker,
+            fee_recipient,
+            token_mint_a,
+            token_mint_b,
+            token_program_a,
+        }
+    }
+
+    async fn make_offer(&mut self, id: u64, token_a_offered_amount: u64, token_b_wanted_amount: u64) -> std::result::Result<(), BanksClientError> {
+        self.make_offer_for(id, token_a_offered_amount, token_b_wanted_amount, None).await
+    }
+
+    async fn make_offer_for(
+        &mut self,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        allowed_taker: Option<Pubkey>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let maker = self.maker.pubkey();
+        let offer = find_offer_address(&maker, id).0;
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        let (token_mint_a, token_mint_b) = (token_mint(self.token_mint_a), token_mint(self.token_mint_b));
+
+        let instruction = Instruction {
+            program_id: swap::ID,
+            accounts: swap::accounts::MakeOffer {
+                maker,
+                token_mint_a,
+                token_mint_b,
+                maker_token_account_a: token_mint_a.map(|mint| get_associated_token_address_with_program_id(&maker, &mint, &self.token_program_a)),
+                offer,
+                vault: token_mint_a.map(|mint| get_vault_address(&offer, &mint, &self.token_program_a)),
+                order_book: find_order_book_address(&self.token_mint_a, &self.token_mint_b).0,
+                system_program: system_program::ID,
+                token_program_a: token_mint_a.map(|_| self.token_program_a),
+                token_program_b: token_mint_b.map(|_| spl_token::ID),
+                associated_token_program: associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: swap::instruction::MakeOffer {
+                id,
+                token_a_offered_amount,
+                token_b_wanted_amount,
+                expires_at: clock.unix_timestamp + 3_600,
+                allowed_taker,
+            }
+            .data(),
+        };
+
+        let maker = self.maker.insecure_clone();
+        process(&mut self.context, &[instruction], &[&maker]).await
+    }
+
+    /// Takes the maker's offer `id`, passing `token_mint_a` as the offered mint so
+    /// tests can substitute a different one.
+    async fn take_offer(&mut self, id: u64, token_mint_a: Pubkey, token_b_amount: u64) -> std::result::Result<(), BanksClientError> {
+        let maker = self.maker.pubkey();
+        let taker = self.taker.pubkey();
+        let fee_recipient = self.fee_recipient;
+        let offer = find_offer_address(&maker, id).0;
+        let (offered_mint, token_mint_a, token_mint_b) = (token_mint(self.token_mint_a), token_mint(token_mint_a), token_mint(self.token_mint_b));
+
+        let instruction = Instruction {
+            program_id: swap::ID,
+            accounts: swap::accounts::TakeOffer {
+                taker,
+                maker,
+                taker_token_account_a: token_mint_a.map(|mint| get_associated_token_address_with_program_id(&taker, &mint, &self.token_program_a)),
+                taker_token_account_b: token_mint_b.map(|mint| get_associated_token_address(&taker, &mint)),
+                maker_token_account_b: token_mint_b.map(|mint| get_associated_token_address(&maker, &mint)),
+                config: find_config_address().0,
+                fee_recipient,
+                fee_token_account_b: token_mint_b.map(|mint| get_associated_token_address(&fee_recipient, &mint)),
+                offer,
+                vault: offered_mint.map(|mint| get_vault_address(&offer, &mint, &self.token_program_a)),
+                order_book: find_order_book_address(&self.token_mint_a, &self.token_mint_b).0,
+                token_mint_a,
+                token_mint_b,
+                system_program: system_program::ID,
+                token_program_a: token_mint_a.map(|_| self.token_program_a),
+                token_program_b: token_mint_b.map(|_| spl_token::ID),
+                associated_token_program: associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: swap::instruction::TakeOffer { token_b_amount }.data(),
+        };
+
+        let taker = self.taker.insecure_clone();
+        process(&mut self.context, &[instruction], &[&taker]).await
+    }
+
+    /// Fills the maker's offers `ids`, passed to the program in the given order.
+    async fn market_take(
+        &mut self,
+        ids: &[u64],
+        max_token_b_amount: u64,
+        worst_price_a: u64,
+        worst_price_b: u64,
+    ) -> std::result::Result<(), BanksClientError> {
+        let maker = self.maker.pubkey();
+        let taker = self.taker.pubkey();
+        let (token_mint_a, token_mint_b, fee_recipient) = (self.token_mint_a, self.token_mint_b, self.fee_recipient);
+
+        let mut accounts = swap::accounts::MarketTake {
+            taker,
+            taker_token_account_a: get_associated_token_address_with_program_id(&taker, &token_mint_a, &self.token_program_a),
+            taker_token_account_b: get_associated_token_address(&taker, &token_mint_b),
+            config: find_config_address().0,
+            fee_recipient,
+            fee_token_account_b: get_associated_token_address(&fee_recipient, &token_mint_b),
+            order_book: find_order_book_address(&token_mint_a, &token_mint_b).0,
+            token_mint_a,
+            token_mint_b,
+            system_program: system_program::ID,
+            token_program_a: self.token_program_a,
+            token_program_b: spl_token::ID,
+            associated_token_program: associated_token::ID,
+        }
+        .to_account_metas(None);
+        for id in ids {
+            let offer = find_offer_address(&maker, *id).0;
+            accounts.extend([
+                AccountMeta::new(offer, false),
+                AccountMeta::new(maker, false),
+                AccountMeta::new(get_vault_address(&offer, &token_mint_a, &self.token_program_a), false),
+                AccountMeta::new(get_associated_token_address(&maker, &token_mint_b), false),
+            ]);
+        }
+
+        let instruction = Instruction {
+            program_id: swap::ID,
+            accounts,
+            data: swap::instruction::MarketTake {
+                offer_count: ids.len() as u8,
+                max_token_b_amount,
+                worst_price_a,
+                worst_price_b,
+            }
+            .data(),
+        };
+
+        let taker = self.taker.insecure_clone();
+        process(&mut self.context, &[instruction], &[&taker]).await
+    }
+
+    async fn token_balance(&mut self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        let token_program = if *mint == self.token_mint_a { self.token_program_a } else { spl_token::ID };
+        let address = get_associated_token_address_with_program_id(owner, mint, &token_program);
+        match self.context.banks_client.get_account(address).await.unwrap() {
+            Some(account) => StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount,
+            None => 0,
+        }
+    }
+
+    async fn order_book_entries(&mut self) -> Vec<swap::OrderBookEntry> {
+        let address = find_order_book_address(&self.token_mint_a, &self.token_mint_b).0;
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        let order_book: swap::OrderBook = bytemuck::pod_read_unaligned(&account.data[swap::ANCHOR_DISCRIMINATOR..]);
+        order_book.entries().to_vec()
+    }
+
+    async fn lamports(&mut self, address: Pubkey) -> u64 {
+        self.context.banks_client.get_balance(address).await.unwrap()
+    }
+
+    async fn account_exists(&mut self, address: Pubkey) -> bool {
+        self.context.banks_client.get_account(address).await.unwrap().is_some()
+    }
+}
+
+/// Seeds the config directly, since `initialize_config` needs the upgrade authority
+/// of a deployed program and the builtin processor has none.
+fn add_config(program_test: &mut ProgramTest, admin: Pubkey, fee_recipient: Pubkey) {
+    let (config, bump) = find_config_address();
+    let mut data = Vec::new();
+    swap::Config {
+        admin,
+        fee_basis_points: FEE_BASIS_POINTS,
+        fee_recipient,
+        bump,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+
+    program_test.add_account(
+        config,
+        solana_sdk::account::Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: swap::ID,
+            ..Default::default()
+        },
+    );
+    program_test.add_account(
+        fee_recipient,
+        solana_sdk::account::Account {
+            lamports: Rent::default().minimum_balance(0),
+            ..Default::default()
+        },
+    );
+}
+
+/// The mint to pass for a leg, or `None` for a native SOL leg.
+fn token_mint(mint: Pubkey) -> Option<Pubkey> {
+    (mint != NATIVE_SOL_SENTINEL).then_some(mint)
+}
+
+/// Deploys the SBF build of the program behind the upgradeable loader with
+/// `upgrade_authority`. `initialize_config` reads the program data account, which only
+/// such a deployment has, so the builtin processor cannot stand in for it here.
+async fn start_deployed(upgrade_authority: Pubkey) -> ProgramTestContext {
+    let elf = read_file(find_file("swap.so").expect("swap.so not found, build it with `cargo build-sbf`"));
+    let program_data = find_program_data_address();
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+
+    let mut program_data_account = solana_sdk::account::Account::new_data_with_space(
+        Rent::default().minimum_balance(metadata_len + elf.len()),
+        &UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(upgrade_authority),
+        },
+        metadata_len + elf.len(),
+        &bpf_loader_upgradeable::ID,
+    )
+    .unwrap();
+    program_data_account.data[metadata_len..].copy_from_slice(&elf);
+
+    let mut program_account = solana_sdk::account::Account::new_data(
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_program()),
+        &UpgradeableLoaderState::Program { programdata_address: program_data },
+        &bpf_loader_upgradeable::ID,
+    )
+    .unwrap();
+    program_account.executable = true;
+
+    let mut program_test = ProgramTest::default();
+    program_test.add_account(program_data, program_data_account);
+    program_test.add_account(swap::ID, program_account);
+    program_test.start_with_context().await
+}
+
+fn find_program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[swap::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+fn initialize_config(admin: Pubkey) -> Instruction {
+    Instruction {
+        program_id: swap::ID,
+        accounts: swap::accounts::InitializeConfig {
+            admin,
+            config: find_config_address().0,
+            program: swap::ID,
+            program_data: find_program_data_address(),
+            fee_recipient: admin,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: swap::instruction::InitializeConfig { fee_basis_points: FEE_BASIS_POINTS }.data(),
+    }
+}
+
+async fn process(context: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> std::result::Result<(), BanksClientError> {
+    // A fresh blockhash keeps otherwise identical transactions, like a repeated take, from
+    // being deduplicated.
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let transaction = Transaction::new_signed_with_payer(instructions, Some(&context.payer.pubkey()), &all_signers, blockhash);
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer, None, 6).unwrap(),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    mint.pubkey()
+}
+
+async fn create_transfer_fee_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(&payer, &mint.pubkey(), rent.minimum_balance(space), space as u64, &spl_token_2022::ID),
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                Some(&payer),
+                Some(&payer),
+                TRANSFER_FEE_BASIS_POINTS,
+                u64::MAX,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint2(&spl_token_2022::ID, &mint.pubkey(), &payer, None, 6).unwrap(),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    mint.pubkey()
+}
+
+async fn mint_to_owner(context: &mut ProgramTestContext, mint: &Pubkey, owner: &Pubkey, amount: u64, token_program: &Pubkey) {
+    let payer = context.payer.pubkey();
+    let token_account = get_associated_token_address_with_program_id(owner, mint, token_program);
+
+    process(
+        context,
+        &[
+            spl_associated_token_account::instruction::create_associated_token_account(&payer, owner, mint, token_program),
+            spl_token_2022::instruction::mint_to(token_program, mint, &token_account, &payer, &[], amount).unwrap(),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+fn assert_custom_error(result: std::result::Result<(), BanksClientError>, code: u32) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(actual)) => assert_eq!(actual, code),
+        other => panic!("expected custom error {code}, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs the SBF build, run with `cargo test-sbf -- --ignored`"]
+async fn initialize_config_requires_upgrade_authority() {
+    let upgrade_authority = Keypair::new();
+    let mut context = start_deployed(upgrade_authority.pubkey()).await;
+    let attacker = context.payer.pubkey();
+    let config = find_config_address().0;
+
+    process(
+        &mut context,
+        &[system_instruction::transfer(&attacker, &upgrade_authority.pubkey(), 1_000_000_000)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_custom_error(
+        process(&mut context, &[initialize_config(attacker)], &[]).await,
+        swap::error::ErrorCode::Unauthorized.into(),
+    );
+    assert!(context.banks_client.get_account(config).await.unwrap().is_none());
+
+    process(&mut context, &[initialize_config(upgrade_authority.pubkey())], &[&upgrade_authority])
+        .await
+        .unwrap();
+
+    let account = context.banks_client.get_account(config).await.unwrap().unwrap();
+    let config = swap::Config::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(config.admin, upgrade_authority.pubkey());
+    assert_eq!(config.fee_basis_points, FEE_BASIS_POINTS);
+}
+
+#[tokio::test]
+async fn take_offer_swaps_tokens_and_closes_offer() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b, fee_recipient) = (env.token_mint_a, env.token_mint_b, env.fee_recipient);
+    let offer = find_offer_address(&maker, 1).0;
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+    assert_eq!(env.token_balance(&maker, &mint_a).await, STARTING_BALANCE - 500);
+    assert_eq!(env.token_balance(&offer, &mint_a).await, 500);
+
+    env.take_offer(1, mint_a, 1_000).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 500);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE - 1_000);
+    assert_eq!(env.token_balance(&maker, &mint_b).await, 990);
+    assert_eq!(env.token_balance(&fee_recipient, &mint_b).await, 10);
+    assert!(!env.account_exists(offer).await);
+    assert!(!env.account_exists(get_vault_address(&offer, &mint_a, &spl_token::ID)).await);
+}
+
+#[tokio::test]
+async fn take_offer_rejects_wrong_mint() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let mint_a = env.token_mint_a;
+    let offer = find_offer_address(&maker, 1).0;
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+
+    // A worthless mint the attacker controls, passed in place of the offered one.
+    let fake_mint = create_mint(&mut env.context).await;
+    assert_custom_error(
+        env.take_offer(1, fake_mint, 1_000).await,
+        anchor_lang::error::ErrorCode::ConstraintRaw as u32,
+    );
+
+    assert!(env.account_exists(offer).await);
+    assert_eq!(env.token_balance(&offer, &mint_a).await, 500);
+}
+
+#[tokio::test]
+async fn take_offer_fails_without_enough_token_b() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b) = (env.token_mint_a, env.token_mint_b);
+    let offer = find_offer_address(&maker, 1).0;
+
+    env.make_offer(1, 500, STARTING_BALANCE + 1).await.unwrap();
+
+    assert_custom_error(
+        env.take_offer(1, mint_a, STARTING_BALANCE + 1).await,
+        spl_token::error::TokenError::InsufficientFunds as u32,
+    );
+
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE);
+    assert_eq!(env.token_balance(&offer, &mint_a).await, 500);
+}
+
+#[tokio::test]
+async fn take_offer_cannot_be_repeated() {
+    let mut env = Env::new().await;
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b) = (env.token_mint_a, env.token_mint_b);
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+    env.take_offer(1, mint_a, 1_000).await.unwrap();
+
+    assert_custom_error(
+        env.take_offer(1, mint_a, 1_000).await,
+        anchor_lang::error::ErrorCode::AccountNotInitialized as u32,
+    );
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 500);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE - 1_000);
+}
+
+#[tokio::test]
+async fn take_offer_closes_vault_holding_withheld_transfer_fees() {
+    let mut env = Env::with_token_program_a(spl_token_2022::ID).await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let mint_a = env.token_mint_a;
+    let offer = find_offer_address(&maker, 1).0;
+    let vault = get_vault_address(&offer, &mint_a, &spl_token_2022::ID);
+
+    // 1% of the deposit stays withheld in the vault, which would otherwise block closing it.
+    env.make_offer(1, 1_000, 1_000).await.unwrap();
+    assert_eq!(env.token_balance(&offer, &mint_a).await, 990);
+
+    env.take_offer(1, mint_a, 1_000).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 980);
+    assert!(!env.account_exists(offer).await);
+    assert!(!env.account_exists(vault).await);
+
+    let mint_account = env.context.banks_client.get_account(mint_a).await.unwrap().unwrap();
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data).unwrap();
+    let transfer_fee_config = mint_state.get_extension::<transfer_fee::TransferFeeConfig>().unwrap();
+    assert_eq!(u64::from(transfer_fee_config.withheld_amount), 10);
+}
+
+#[tokio::test]
+async fn last_fill_sweeps_tokens_sent_to_the_vault() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let payer = env.context.payer.pubkey();
+    let mint_a = env.token_mint_a;
+    let offer = find_offer_address(&maker, 1).0;
+    let vault = get_vault_address(&offer, &mint_a, &spl_token::ID);
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+    env.take_offer(1, mint_a, 400).await.unwrap();
+
+    // Anyone can top up the vault, and a vault with a balance cannot be closed.
+    process(
+        &mut env.context,
+        &[spl_token::instruction::mint_to(&spl_token::ID, &mint_a, &vault, &payer, &[], 7).unwrap()],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    env.take_offer(1, mint_a, 600).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 507);
+    assert!(!env.account_exists(offer).await);
+    assert!(!env.account_exists(vault).await);
+}
+
+#[tokio::test]
+async fn offers_for_a_pair_without_an_order_book_are_made_unlisted() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let mint_a = env.token_mint_a;
+    let offer = find_offer_address(&maker, 1).0;
+
+    let unlisted_mint_b = create_mint(&mut env.context).await;
+    mint_to_owner(&mut env.context, &unlisted_mint_b, &taker, STARTING_BALANCE, &spl_token::ID).await;
+    env.token_mint_b = unlisted_mint_b;
+    assert!(!env.account_exists(find_order_book_address(&mint_a, &unlisted_mint_b).0).await);
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+    env.take_offer(1, mint_a, 1_000).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 500);
+    assert_eq!(env.token_balance(&maker, &unlisted_mint_b).await, 990);
+    assert!(!env.account_exists(offer).await);
+}
+
+#[tokio::test]
+async fn update_config_requires_rent_exempt_fee_recipient() {
+    let mut env = Env::new().await;
+    let admin = env.admin.insecure_clone();
+    let config = find_config_address().0;
+    let update_config = |fee_recipient| Instruction {
+        program_id: swap::ID,
+        accounts: swap::accounts::UpdateConfig {
+            admin: admin.pubkey(),
+            config,
+            fee_recipient,
+        }
+        .to_account_metas(None),
+        data: swap::instruction::UpdateConfig { fee_basis_points: 50 }.data(),
+    };
+
+    // Native SOL fees below the rent-exempt minimum could never be paid to this account.
+    assert_custom_error(
+        process(&mut env.context, &[update_config(Keypair::new().pubkey())], &[&admin]).await,
+        swap::error::ErrorCode::FeeRecipientNotRentExempt.into(),
+    );
+
+    let fee_recipient = env.taker.pubkey();
+    process(&mut env.context, &[update_config(fee_recipient)], &[&admin])
+        .await
+        .unwrap();
+
+    let account = env.context.banks_client.get_account(config).await.unwrap().unwrap();
+    let config = swap::Config::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(config.fee_recipient, fee_recipient);
+    assert_eq!(config.fee_basis_points, 50);
+}
+
+#[tokio::test]
+async fn take_offer_trades_native_sol_for_tokens() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let (mint_b, fee_recipient) = (env.token_mint_b, env.fee_recipient);
+    let offer = find_offer_address(&maker, 1).0;
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+    env.token_mint_a = NATIVE_SOL_SENTINEL;
+
+    env.make_offer(1, 500_000_000, 1_000).await.unwrap();
+    let taker_lamports = env.lamports(taker).await;
+
+    env.take_offer(1, NATIVE_SOL_SENTINEL, 1_000).await.unwrap();
+
+    // The taker also pays for the maker's and the fee recipient's token B accounts.
+    let token_account_rent = rent.minimum_balance(spl_token::state::Account::LEN);
+    assert_eq!(env.lamports(taker).await, taker_lamports + 500_000_000 - 2 * token_account_rent);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE - 1_000);
+    assert_eq!(env.token_balance(&maker, &mint_b).await, 990);
+    assert_eq!(env.token_balance(&fee_recipient, &mint_b).await, 10);
+    assert!(!env.account_exists(offer).await);
+}
+
+#[tokio::test]
+async fn take_offer_trades_tokens_for_native_sol() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let (mint_a, fee_recipient) = (env.token_mint_a, env.fee_recipient);
+    let offer = find_offer_address(&maker, 1).0;
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+    env.token_mint_b = NATIVE_SOL_SENTINEL;
+
+    env.make_offer(1, 500, 1_000_000).await.unwrap();
+    let maker_lamports = env.lamports(maker).await;
+    let offer_lamports = env.lamports(offer).await;
+
+    env.take_offer(1, mint_a, 1_000_000).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 500);
+    assert_eq!(env.lamports(maker).await, maker_lamports + 990_000 + offer_lamports);
+    assert_eq!(env.lamports(fee_recipient).await, rent.minimum_balance(0) + 10_000);
+    assert!(!env.account_exists(offer).await);
+    assert!(!env.account_exists(get_vault_address(&offer, &mint_a, &spl_token::ID)).await);
+}
+
+#[tokio::test]
+async fn private_offers_are_not_listed() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+
+    env.make_offer_for(1, 500, 1_000, Some(taker)).await.unwrap();
+    assert!(env.order_book_entries().await.is_empty());
+
+    env.make_offer(2, 500, 1_000).await.unwrap();
+    let entries = env.order_book_entries().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].offer, find_offer_address(&maker, 2).0);
+}
+
+/// Offers 1 to 3 sell 500 token A each at 2, 3 and 4 token B apiece. `market_take` needs
+/// the maker's token B account to exist already.
+async fn make_market(env: &mut Env) {
+    let maker = env.maker.pubkey();
+    let mint_b = env.token_mint_b;
+    mint_to_owner(&mut env.context, &mint_b, &maker, 0, &spl_token::ID).await;
+
+    env.make_offer(1, 500, 1_000).await.unwrap();
+    env.make_offer(2, 500, 1_500).await.unwrap();
+    env.make_offer(3, 500, 2_000).await.unwrap();
+}
+
+#[tokio::test]
+async fn market_take_fills_best_price_first_and_partially_fills_the_last_offer() {
+    let mut env = Env::new().await;
+    let maker = env.maker.pubkey();
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b, fee_recipient) = (env.token_mint_a, env.token_mint_b, env.fee_recipient);
+    make_market(&mut env).await;
+
+    // Enough for all of offers 1 and 2 and half of offer 3.
+    env.market_take(&[1, 2, 3], 3_500, 1, 4).await.unwrap();
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 1_250);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE - 3_500);
+    assert_eq!(env.token_balance(&maker, &mint_b).await, 3_465);
+    assert_eq!(env.token_balance(&fee_recipient, &mint_b).await, 35);
+    assert!(!env.account_exists(find_offer_address(&maker, 1).0).await);
+    assert!(!env.account_exists(find_offer_address(&maker, 2).0).await);
+
+    let last_offer = find_offer_address(&maker, 3).0;
+    assert_eq!(env.token_balance(&last_offer, &mint_a).await, 250);
+
+    let entries = env.order_book_entries().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].offer, entries[0].remaining_a, entries[0].remaining_b), (last_offer, 250, 1_000));
+}
+
+#[tokio::test]
+async fn market_take_reverts_when_an_offer_exceeds_the_worst_price() {
+    let mut env = Env::new().await;
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b) = (env.token_mint_a, env.token_mint_b);
+    make_market(&mut env).await;
+
+    // Offer 3 asks 4 token B per token A, above the taker's limit of 3.
+    assert_custom_error(
+        env.market_take(&[1, 2, 3], 4_500, 1, 3).await,
+        swap::error::ErrorCode::WorstPriceExceeded.into(),
+    );
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 0);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE);
+    assert_eq!(env.order_book_entries().await.len(), 3);
+}
+
+#[tokio::test]
+async fn market_take_rejects_offers_out_of_price_order() {
+    let mut env = Env::new().await;
+    let taker = env.taker.pubkey();
+    let (mint_a, mint_b) = (env.token_mint_a, env.token_mint_b);
+    make_market(&mut env).await;
+
+    assert_custom_error(
+        env.market_take(&[2, 1], 2_500, 1, 4).await,
+        swap::error::ErrorCode::OffersNotSorted.into(),
+    );
+
+    assert_eq!(env.token_balance(&taker, &mint_a).await, 0);
+    assert_eq!(env.token_balance(&taker, &mint_b).await, STARTING_BALANCE);
+    assert_eq!(env.order_book_entries().await.len(), 3);
+}